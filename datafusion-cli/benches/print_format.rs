@@ -0,0 +1,101 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+extern crate criterion;
+
+use std::sync::Arc;
+
+use arrow::array::{Float64Array, Int64Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use criterion::{criterion_group, criterion_main, Criterion};
+use datafusion::config::FormatOptions;
+use datafusion_cli::print_format::{OutputStreamState, PrintBatchesOptions, PrintFormat};
+use datafusion_cli::print_options::MaxRows;
+
+const ROWS: usize = 1_000_000;
+const BATCH_SIZE: usize = 8192;
+
+/// A result of `ROWS` rows in batches of `BATCH_SIZE` rows sharing a schema
+fn batches() -> (SchemaRef, Vec<RecordBatch>) {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("price", DataType::Float64, true),
+    ]));
+    let batches = (0..ROWS)
+        .step_by(BATCH_SIZE)
+        .map(|offset| {
+            let ids = offset as i64..(offset + BATCH_SIZE).min(ROWS) as i64;
+            RecordBatch::try_new(
+                Arc::clone(&schema),
+                vec![
+                    Arc::new(Int64Array::from_iter_values(ids.clone())),
+                    Arc::new(StringArray::from_iter(
+                        ids.clone()
+                            .map(|id| (id % 7 != 0).then(|| format!("name {id}"))),
+                    )),
+                    Arc::new(Float64Array::from_iter(
+                        ids.map(|id| (id % 5 != 0).then_some(id as f64 / 4.0)),
+                    )),
+                ],
+            )
+            .unwrap()
+        })
+        .collect();
+    (schema, batches)
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let (schema, batches) = batches();
+    let mut group = c.benchmark_group("print_1m_rows");
+    group.sample_size(10);
+
+    group.bench_function("table", |b| {
+        b.iter(|| {
+            PrintFormat::Table
+                .print_with_options(
+                    &mut std::io::sink(),
+                    Arc::clone(&schema),
+                    &batches,
+                    &PrintBatchesOptions::new().with_colorize(true),
+                )
+                .unwrap()
+        })
+    });
+
+    group.bench_function("table_stream", |b| {
+        b.iter(|| {
+            let mut state = OutputStreamState::new(
+                std::io::sink(),
+                Arc::clone(&schema),
+                MaxRows::Unlimited,
+                &FormatOptions::default(),
+            )
+            .with_options(PrintBatchesOptions::new().with_colorize(true));
+            for batch in &batches {
+                state.process_batch(batch).unwrap();
+            }
+            state.finish().unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
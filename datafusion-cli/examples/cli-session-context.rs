@@ -88,7 +88,8 @@ pub async fn main() {
         format: datafusion_cli::print_format::PrintFormat::Automatic,
         quiet: false,
         maxrows: datafusion_cli::print_options::MaxRows::Unlimited,
-        color: true,
+        color: datafusion_cli::print_options::ColorChoice::Always,
+        ..Default::default()
     };
 
     exec_from_repl(&my_ctx, &mut print_options).await.unwrap();
@@ -48,6 +48,8 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::io::IsTerminal;
+use std::path::Path;
 use tokio::signal;
 
 /// run and execute SQL statements and commands, against a context with the given print options
@@ -133,7 +135,7 @@ pub async fn exec_from_repl(
     let mut rl = Editor::new()?;
     rl.set_helper(Some(CliHelper::new(
         &ctx.task_ctx().session_config().options().sql_parser.dialect,
-        print_options.color,
+        print_options.color.enabled(),
     )));
     rl.load_history(".history").ok();
 
@@ -268,6 +270,22 @@ impl StatementExecutor {
         // Track memory usage for the query result if it's bounded
         let mut reservation =
             MemoryConsumer::new("DataFusion-Cli").register(task_ctx.memory_pool());
+        let (automatic, reason) = resolve_automatic(
+            print_options.output.as_deref(),
+            std::io::stdout().is_terminal(),
+        );
+        let resolved = adjusted.into_inner(automatic);
+
+        // the columns are described without executing the plan
+        if resolved.describe {
+            log_automatic_decision(
+                print_options,
+                &automatic,
+                reason,
+                &mut std::io::stderr(),
+            )?;
+            return resolved.print_schema(&physical_plan.schema(), &options.format);
+        }
 
         if physical_plan.boundedness().is_unbounded() {
             if physical_plan.pipeline_behavior() == EmissionType::Final {
@@ -279,16 +297,20 @@ impl StatementExecutor {
             // As the input stream comes, we can generate results.
             // However, memory safety is not guaranteed.
             let stream = execute_stream(physical_plan, task_ctx.clone())?;
-            print_options
-                .print_stream(stream, now, &options.format)
-                .await?;
+            log_automatic_decision(
+                print_options,
+                &automatic,
+                reason,
+                &mut std::io::stderr(),
+            )?;
+            resolved.print_stream(stream, now, &options.format).await?;
         } else {
             // Bounded stream; collected results size is limited by the maxrows option
             let schema = physical_plan.schema();
             let mut stream = execute_stream(physical_plan, task_ctx.clone())?;
             let mut results = vec![];
             let mut row_count = 0_usize;
-            let max_rows = match print_options.maxrows {
+            let max_rows = match resolved.maxrows {
                 MaxRows::Unlimited => usize::MAX,
                 MaxRows::Limited(n) => n,
             };
@@ -297,20 +319,20 @@ impl StatementExecutor {
                 let curr_num_rows = batch.num_rows();
                 // Stop collecting results if the number of rows exceeds the limit
                 // results batch should include the last batch that exceeds the limit
-                if row_count < max_rows + curr_num_rows {
+                if row_count < max_rows.saturating_add(curr_num_rows) {
                     // Try to grow the reservation to accommodate the batch in memory
                     reservation.try_grow(get_record_batch_memory_size(&batch))?;
                     results.push(batch);
                 }
                 row_count += curr_num_rows;
             }
-            adjusted.into_inner().print_batches(
-                schema,
-                &results,
-                now,
-                row_count,
-                &options.format,
+            log_automatic_decision(
+                print_options,
+                &automatic,
+                reason,
+                &mut std::io::stderr(),
             )?;
+            resolved.print_batches(schema, &results, now, row_count, &options.format)?;
             reservation.free();
         }
 
@@ -347,6 +369,43 @@ impl StatementExecutor {
     }
 }
 
+/// The format [`PrintFormat::Automatic`] resolves to, and why: the format of
+/// the extension of the `output` file, a [`PrintFormat::Table`] for a person
+/// reading a terminal, like psql does, and [`PrintFormat::Csv`] for a program
+/// reading a pipe or a file
+fn resolve_automatic(
+    output: Option<&Path>,
+    stdout_is_terminal: bool,
+) -> (PrintFormat, &'static str) {
+    if let Some(output) = output {
+        return match PrintFormat::from_extension(output) {
+            Some(format) => (format, "the output file extension"),
+            None => (PrintFormat::Csv, "the output is a file"),
+        };
+    }
+    if stdout_is_terminal {
+        (PrintFormat::Table, "stdout is a terminal")
+    } else {
+        (PrintFormat::Csv, "stdout is not a terminal")
+    }
+}
+
+/// Write the format [`PrintFormat::Automatic`] resolves to, and why, to `log`
+/// if [`PrintOptions::log_automatic_decision`] is set
+fn log_automatic_decision(
+    print_options: &PrintOptions,
+    resolved: &PrintFormat,
+    reason: &str,
+    log: &mut impl Write,
+) -> Result<()> {
+    if print_options.log_automatic_decision
+        && print_options.format == PrintFormat::Automatic
+    {
+        writeln!(log, "automatic -> {} ({reason})", resolved.output_name())?;
+    }
+    Ok(())
+}
+
 /// Track adjustments to the print options based on the plan / statement being executed
 #[derive(Debug)]
 struct AdjustedPrintOptions {
@@ -383,10 +442,18 @@ impl AdjustedPrintOptions {
         self
     }
 
-    /// Finalize and return the inner `PrintOptions`
-    fn into_inner(mut self) -> PrintOptions {
+    /// Finalize and return the inner `PrintOptions`, with
+    /// [`PrintFormat::Automatic`] resolved to `automatic`
+    fn into_inner(mut self, automatic: PrintFormat) -> PrintOptions {
         if self.inner.format == PrintFormat::Automatic {
-            self.inner.format = PrintFormat::Table;
+            self.inner.format = automatic;
+        }
+        // the default row limit is for reading a Table, not for exporting,
+        // such as the CSV of an automatic format redirected to a file
+        let exported =
+            self.inner.format != PrintFormat::Table || self.inner.output.is_some();
+        if exported && !self.inner.maxrows_explicit {
+            self.inner.maxrows = MaxRows::Unlimited;
         }
 
         self.inner
@@ -516,8 +583,90 @@ mod tests {
     use datafusion::common::plan_err;
 
     use datafusion::prelude::SessionContext;
+    use std::path::PathBuf;
     use url::Url;
 
+    #[test]
+    fn log_automatic_decision_to_sink() -> Result<()> {
+        let mut print_options = PrintOptions {
+            format: PrintFormat::Automatic,
+            maxrows: MaxRows::Unlimited,
+            log_automatic_decision: true,
+            ..Default::default()
+        };
+        let mut stderr: Vec<u8> = vec![];
+        let (automatic, reason) = resolve_automatic(None, true);
+        log_automatic_decision(&print_options, &automatic, reason, &mut stderr)?;
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            "automatic -> table (stdout is a terminal)\n"
+        );
+
+        // nothing is logged for an explicit format
+        print_options.format = PrintFormat::Csv;
+        let mut stderr: Vec<u8> = vec![];
+        let (automatic, reason) = resolve_automatic(None, false);
+        log_automatic_decision(&print_options, &automatic, reason, &mut stderr)?;
+        assert!(stderr.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_automatic_for_stdout() {
+        assert_eq!(resolve_automatic(None, true).0, PrintFormat::Table);
+        assert_eq!(resolve_automatic(None, false).0, PrintFormat::Csv);
+    }
+
+    #[test]
+    fn default_maxrows_only_limits_table() {
+        let resolve = |format, automatic, output: Option<&str>, maxrows_explicit| {
+            let print_options = PrintOptions {
+                format,
+                maxrows: MaxRows::Limited(40),
+                maxrows_explicit,
+                output: output.map(PathBuf::from),
+                ..Default::default()
+            };
+            AdjustedPrintOptions::new(print_options)
+                .into_inner(automatic)
+                .maxrows
+        };
+        let (table, csv) = (PrintFormat::Table, PrintFormat::Csv);
+        let limited = MaxRows::Limited(40);
+        assert_eq!(resolve(table, table, None, false), limited);
+        assert_eq!(resolve(csv, table, None, false), MaxRows::Unlimited);
+        assert_eq!(resolve(csv, table, None, true), limited);
+        assert_eq!(
+            resolve(table, table, Some("out.txt"), false),
+            MaxRows::Unlimited
+        );
+        assert_eq!(
+            resolve(PrintFormat::Parquet, table, Some("out.parquet"), true),
+            limited
+        );
+
+        // automatic is a Table on a terminal, and CSV otherwise
+        let automatic = PrintFormat::Automatic;
+        assert_eq!(resolve(automatic, table, None, false), limited);
+        assert_eq!(resolve(automatic, csv, None, false), MaxRows::Unlimited);
+        assert_eq!(resolve(automatic, csv, None, true), limited);
+    }
+
+    #[test]
+    fn resolve_automatic_for_output_file() {
+        let output = Path::new("out.parquet");
+        assert_eq!(
+            resolve_automatic(Some(output), true),
+            (PrintFormat::Parquet, "the output file extension")
+        );
+        let output = Path::new("out");
+        assert_eq!(
+            resolve_automatic(Some(output), true),
+            (PrintFormat::Csv, "the output is a file")
+        );
+    }
+
     async fn create_external_table_test(location: &str, sql: &str) -> Result<()> {
         let ctx = SessionContext::new();
         let plan = ctx.state().create_logical_plan(sql).await?;
@@ -570,6 +719,56 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn output_file_holds_every_query_of_the_session() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let ctx = SessionContext::new();
+        let commands = || vec!["select 1 as a; select 2 as a".to_string()];
+
+        let print_options = PrintOptions {
+            format: PrintFormat::Csv,
+            quiet: true,
+            output: Some(dir.path().join("out.csv")),
+            ..Default::default()
+        };
+        exec_from_commands(&ctx, commands(), &print_options).await?;
+        // the rows of the second query continue those of the first
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out.csv"))?,
+            "a\n1\n2\n"
+        );
+
+        // a Parquet file holds a single query
+        let print_options = PrintOptions {
+            format: PrintFormat::Parquet,
+            quiet: true,
+            output: Some(dir.path().join("out.parquet")),
+            ..Default::default()
+        };
+        let err = exec_from_commands(&ctx, commands(), &print_options)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot be appended to"), "{err}");
+
+        // a failed query leaves no Parquet file behind to block a retry
+        let print_options = PrintOptions {
+            format: PrintFormat::Parquet,
+            quiet: true,
+            output: Some(dir.path().join("failed.parquet")),
+            ..Default::default()
+        };
+        let failing = vec!["select * from missing_table".to_string()];
+        assert!(exec_from_commands(&ctx, failing, &print_options)
+            .await
+            .is_err());
+        assert!(!dir.path().join("failed.parquet").exists());
+        exec_from_commands(&ctx, vec!["select 1 as a".to_string()], &print_options)
+            .await?;
+        assert!(dir.path().join("failed.parquet").exists());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn create_object_store_table_http() -> Result<()> {
         // Should be OK
@@ -18,9 +18,10 @@
 use std::collections::HashMap;
 use std::env;
 use std::num::NonZeroUsize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 use datafusion::error::{DataFusionError, Result};
 use datafusion::execution::context::SessionConfig;
@@ -34,8 +35,8 @@ use datafusion_cli::functions::ParquetMetadataFunc;
 use datafusion_cli::{
     exec,
     pool_type::PoolType,
-    print_format::PrintFormat,
-    print_options::{MaxRows, PrintOptions},
+    print_format::{Alignment, BinaryEncoding, BorderStyle, PrintFormat, QuoteStyle},
+    print_options::{terminal_width, ColorChoice, MaxRows, PrintOptions},
     DATAFUSION_CLI_VERSION,
 };
 
@@ -48,6 +49,9 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+/// The number of rows of Table output shown without `--maxrows`
+const DEFAULT_MAXROWS: usize = 40;
+
 #[derive(Debug, Parser, PartialEq)]
 #[clap(author, version, about, long_about= None)]
 struct Args {
@@ -129,13 +133,180 @@ struct Args {
 
     #[clap(
         long,
-        help = "The max number of rows to display for 'Table' format\n[possible values: numbers(0/10/...), inf(no limit)]",
-        default_value = "40"
+        help = "The max number of rows to display, in any format. Without it, 'Table' output shows at most 40 rows and the other formats and --output every row\n[possible values: numbers(0/10/...), inf(no limit)]"
+    )]
+    maxrows: Option<MaxRows>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = ColorChoice::Never,
+        num_args = 0..=1,
+        default_missing_value = "auto",
+        help = "When to highlight SQL syntax and colorize 'Table' output, with bold headers and dimmed nulls. A bare --color means 'auto', coloring only a terminal"
+    )]
+    color: ColorChoice,
+
+    #[clap(
+        long,
+        help = "Print the format the 'automatic' format resolves to, and why, to stderr"
+    )]
+    log_automatic_decision: bool,
+
+    #[clap(
+        long,
+        help = "The string nulls are printed as in 'Table', 'Csv' and 'Tsv' output, e.g. 'NULL' or '\\N'"
+    )]
+    null_string: Option<String>,
+
+    #[clap(
+        long,
+        help = "The max display width of a column, longer values are cut short and end with '…'"
+    )]
+    max_col_width: Option<usize>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = QuoteStyle::Necessary,
+        help = "Which 'Csv' and 'Tsv' fields are quoted"
+    )]
+    quote_style: QuoteStyle,
+
+    #[clap(
+        long,
+        default_value_t = '"',
+        help = "The ASCII character 'Csv' and 'Tsv' fields are quoted with"
+    )]
+    quote_char: char,
+
+    #[clap(
+        long,
+        help = "Prefix 'Table' rows with a '#' column numbering them from 1"
+    )]
+    row_numbers: bool,
+
+    #[clap(
+        long,
+        help = "Don't shrink 'Table' output to fit the terminal width or --terminal-width"
+    )]
+    no_fit_width: bool,
+
+    #[clap(
+        long,
+        help = "The width 'Table' output is shrunk to, also when not printed to a terminal. Defaults to the width the terminal or the COLUMNS environment variable reports, then 80"
+    )]
+    terminal_width: Option<usize>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = BorderStyle::Ascii,
+        help = "The characters 'Table' borders are drawn with"
+    )]
+    border_style: BorderStyle,
+
+    #[clap(
+        long,
+        default_value = "3",
+        help = "The number of dotted lines marking that 'Table' rows were omitted, at least 1"
+    )]
+    truncation_rows: usize,
+
+    #[clap(
+        long,
+        help = "Print the 'Table' header again every this many rows, so it stays in view"
+    )]
+    header_interval: Option<usize>,
+
+    #[clap(
+        long,
+        value_delimiter = ',',
+        value_parser(parse_column_alignment),
+        help = "Align 'Table' columns regardless of their type, e.g. 'name:left,amount:right', by column name or index from 0 [possible alignments: left, right, center]"
+    )]
+    align: Vec<(String, Alignment)>,
+
+    #[clap(
+        long,
+        help = "End 'Csv' and 'Tsv' output with a '# N rows' comment line counting the rows written"
+    )]
+    row_count_comment: bool,
+
+    #[clap(
+        long,
+        help = "Start 'Csv' and 'Tsv' output with a UTF-8 byte order mark, for Excel to read non-ASCII characters"
+    )]
+    csv_bom: bool,
+
+    #[clap(
+        long,
+        help = "The number of decimal places floating point and decimal values are printed with, except in JSON output"
+    )]
+    float_precision: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Print floating point and decimal values in scientific notation, except in JSON output"
+    )]
+    float_scientific: bool,
+
+    #[clap(
+        long,
+        num_args = 0..=1,
+        default_missing_value = ",",
+        help = "Separate the groups of three digits of 'Table' integers and decimals, with ',' unless another separator is given"
+    )]
+    group_digits: Option<char>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = BinaryEncoding::Default,
+        help = "How binary values are printed, except in JSON output"
+    )]
+    binary_encoding: BinaryEncoding,
+
+    #[clap(
+        long,
+        value_delimiter = ',',
+        help = "Print only these columns of the results, in this order, e.g. 'a,c,f', whatever the format"
     )]
-    maxrows: MaxRows,
+    display_columns: Option<Vec<String>>,
 
-    #[clap(long, help = "Enables console syntax highlighting")]
-    color: bool,
+    #[clap(
+        long,
+        help = "Print the name and type of each column of the results instead of their rows"
+    )]
+    describe: bool,
+
+    #[clap(
+        short = 'o',
+        long,
+        help = "Write the results to this file instead of stdout, which must not exist yet unless --force is set. The results of later queries of the session are appended, except to 'parquet' and 'arrow' files, which hold a single query. The 'automatic' format follows its extension"
+    )]
+    output: Option<PathBuf>,
+
+    #[clap(
+        long,
+        requires = "output",
+        help = "Overwrite an existing --output file"
+    )]
+    force: bool,
+
+    #[clap(
+        long,
+        default_value_t = 0,
+        help = "Coalesce streamed output into chunks of at least this many bytes before writing them, 0 writes every batch immediately"
+    )]
+    chunk_target_bytes: usize,
+
+    #[clap(
+        long,
+        requires = "chunk_target_bytes",
+        help = "The most milliseconds streamed output is held back while a --chunk-target-bytes chunk fills up, checked when more output arrives"
+    )]
+    max_flush_delay_ms: Option<u64>,
 
     #[clap(
         short = 'd',
@@ -222,8 +393,38 @@ async fn main_inner() -> Result<()> {
     let mut print_options = PrintOptions {
         format: args.format,
         quiet: args.quiet,
-        maxrows: args.maxrows,
+        maxrows: args.maxrows.unwrap_or(MaxRows::Limited(DEFAULT_MAXROWS)),
+        maxrows_explicit: args.maxrows.is_some(),
         color: args.color,
+        log_automatic_decision: args.log_automatic_decision,
+        null_string: args.null_string,
+        max_col_width: args.max_col_width,
+        quote_style: args.quote_style,
+        quote_char: args.quote_char,
+        row_numbers: args.row_numbers,
+        // the width of the terminal does not apply to an output file
+        fit_width: if args.no_fit_width || args.output.is_some() {
+            None
+        } else {
+            terminal_width(args.terminal_width)
+        },
+        border_style: args.border_style,
+        truncation_rows: args.truncation_rows,
+        header_interval: args.header_interval,
+        column_alignments: args.align.into_iter().collect(),
+        row_count_comment: args.row_count_comment,
+        csv_bom: args.csv_bom,
+        float_precision: args.float_precision,
+        float_scientific: args.float_scientific,
+        group_digits: args.group_digits,
+        binary_encoding: args.binary_encoding,
+        display_columns: args.display_columns,
+        describe: args.describe,
+        output: args.output,
+        force: args.force,
+        output_file: Default::default(),
+        chunk_target_bytes: args.chunk_target_bytes,
+        max_flush_delay: args.max_flush_delay_ms.map(Duration::from_millis),
     };
 
     let commands = args.command;
@@ -316,6 +517,17 @@ fn parse_batch_size(size: &str) -> Result<usize, String> {
     }
 }
 
+fn parse_column_alignment(column: &str) -> Result<(String, Alignment), String> {
+    let Some((name, alignment)) = column.rsplit_once(':') else {
+        return Err(format!(
+            "Invalid column alignment '{column}', expected 'column:alignment'"
+        ));
+    };
+    let alignment = clap::ValueEnum::from_str(alignment, true)
+        .map_err(|_| format!("Invalid alignment '{alignment}' of column '{name}'"))?;
+    Ok((name.to_string(), alignment))
+}
+
 fn parse_command(command: &str) -> Result<String, String> {
     if !command.is_empty() {
         Ok(command.to_string())
@@ -408,6 +620,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn column_alignment() {
+        assert_eq!(
+            parse_column_alignment("name:left"),
+            Ok(("name".to_string(), Alignment::Left))
+        );
+        assert_eq!(
+            parse_column_alignment("a:b:CENTER"),
+            Ok(("a:b".to_string(), Alignment::Center))
+        );
+        assert!(parse_column_alignment("name").is_err());
+        assert!(parse_column_alignment("name:up").is_err());
+    }
+
     #[test]
     fn memory_pool_size() -> Result<(), String> {
         // Test basic sizes without suffix, assumed to be bytes
@@ -17,17 +17,45 @@
 
 //! Print format variants
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::print_options::MaxRows;
 
-use arrow::csv::writer::WriterBuilder;
-use arrow::datatypes::SchemaRef;
-use arrow::json::{ArrayWriter, LineDelimitedWriter};
+use arrow::array::{
+    Array, ArrayRef, AsArray, GenericStringArray, LargeStringArray, OffsetSizeTrait,
+    PrimitiveArray, StringArray, StringViewArray, UInt64Array,
+};
+use arrow::compute::{
+    cast, cast_with_options, concat_batches, take_record_batch, CastOptions,
+};
+use arrow::csv::writer::{Writer as CsvWriter, WriterBuilder};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType, Field, Fields, Float16Type, Float32Type, Float64Type,
+    Schema, SchemaRef, DECIMAL128_MAX_PRECISION, DECIMAL256_MAX_PRECISION,
+};
+use arrow::ipc::writer::StreamWriter;
+use arrow::json::{ArrayWriter, LineDelimitedWriter, WriterBuilder as JsonWriterBuilder};
 use arrow::record_batch::RecordBatch;
-use arrow::util::pretty::pretty_format_batches_with_options;
+use arrow::util::display::{array_value_to_string, ArrayFormatter};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use datafusion::common::exec_err;
+use datafusion::common::instant::Instant;
 use datafusion::config::FormatOptions;
 use datafusion::error::Result;
+use datafusion::functions_aggregate::min_max::{MaxAccumulator, MinAccumulator};
+use datafusion::logical_expr::Accumulator;
+use parking_lot::Mutex;
+use parquet::arrow::ArrowWriter;
+use serde_json::{json, Value};
+use unicode_normalization::UnicodeNormalization;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Allow records to be printed in different formats
 #[derive(Debug, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
@@ -37,7 +65,43 @@ pub enum PrintFormat {
     Table,
     Json,
     NdJson,
+    /// [`Self::Json`] indented by two spaces, with one field per line
+    JsonPretty,
     Automatic,
+    /// Columns padded to the widths [`Self::Table`] would use, without
+    /// borders, see [`PrintBatchesOptions::column_gap`]
+    FixedWidth,
+    /// GitHub flavored Markdown table
+    Markdown,
+    /// HTML `<table>`, see [`PrintBatchesOptions::html_table_class`]
+    Html,
+    /// One `key=value` line per row, for log collectors, see
+    /// [`PrintBatchesOptions::logfmt_level`]
+    Logfmt,
+    /// LaTeX `tabular` with booktabs rules
+    Latex,
+    /// Parquet file at [`PrintBatchesOptions::output_path`], as Parquet
+    /// cannot be written to the terminal
+    Parquet,
+    /// Arrow IPC stream, for Arrow-native readers such as pyarrow
+    Arrow,
+    /// An INI `[row N]` section of `key=value` lines per row, quoting keys and
+    /// values with special characters, see
+    /// [`PrintBatchesOptions::ini_omit_nulls`]
+    Ini,
+    /// A `-[ RECORD N ]-` block of `column | value` lines per row, like the
+    /// expanded display of psql, for rows too wide to read as a table
+    Vertical,
+    /// `{"schema": [...], "rows": [...]}`: the name and data type of each
+    /// column, and the rows as [`Self::Json`] prints them, for readers that
+    /// cannot tell the types from the values
+    JsonWithSchema,
+    /// Cross-tab of two categorical columns and a measure, see [`PivotColumns`].
+    ///
+    /// Not available from the command line as the column roles must be
+    /// configured through [`PrintBatchesOptions::pivot`]
+    #[value(skip)]
+    Pivot,
 }
 
 impl FromStr for PrintFormat {
@@ -48,411 +112,10234 @@ impl FromStr for PrintFormat {
     }
 }
 
-macro_rules! batches_to_json {
-    ($WRITER: ident, $writer: expr, $batches: expr) => {{
-        {
-            if !$batches.is_empty() {
-                let mut json_writer = $WRITER::new(&mut *$writer);
-                for batch in $batches {
-                    json_writer.write(batch)?;
-                }
-                json_writer.finish()?;
-                json_finish!($WRITER, $writer);
-            }
+impl std::fmt::Display for PrintFormat {
+    /// The name of the format on the command line, which parses back to it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match clap::ValueEnum::to_possible_value(self) {
+            Some(value) => write!(f, "{}", value.get_name()),
+            // not available from the command line, see `Self::Pivot`
+            None => write!(f, "{}", self.output_name()),
         }
-        Ok(()) as Result<()>
-    }};
+    }
 }
 
-macro_rules! json_finish {
-    (ArrayWriter, $writer: expr) => {{
-        writeln!($writer)?;
-    }};
-    (LineDelimitedWriter, $writer: expr) => {{}};
+/// Additional options controlling how [`PrintFormat::print_with_options`] renders
+/// the batches
+#[derive(Debug, Clone)]
+pub struct PrintBatchesOptions {
+    /// Unicode normalization applied to string values before they are
+    /// measured and rendered, so visually identical strings print identically
+    pub normalize_unicode: Option<NormalizationForm>,
+    /// The column roles used by [`PrintFormat::Pivot`]
+    pub pivot: Option<PivotColumns>,
+    /// Exact widths of the [`PrintFormat::Table`] columns, one per column.
+    ///
+    /// When set the widths are not computed from the data, so rows are printed
+    /// without buffering, and values are padded or truncated to fit.
+    pub fixed_widths: Option<Vec<usize>>,
+    /// Instead of the rows, print the name, data type and Table display width
+    /// of each column, regardless of the format
+    pub describe: bool,
+    /// Escape every non-ASCII character in [`PrintFormat::Json`] and
+    /// [`PrintFormat::NdJson`] output as `\uXXXX`
+    pub json_ascii_escape: bool,
+    /// Whether [`PrintFormat::Table`] prints the column names. When `false`
+    /// the borders and rows are still printed, which is useful for
+    /// concatenating the output of several queries.
+    pub table_header: bool,
+    /// The `(true, false, null)` tokens printed for boolean values, so that
+    /// a null is never mistaken for `false`. Not applied to JSON output.
+    pub bool_tokens: Option<(String, String, String)>,
+    /// Print a first line describing the format and schema as compact JSON,
+    /// so readers can detect how to parse the rest of the output. The line is
+    /// a comment, so it is left out of formats without comments, such as
+    /// JSON, and of the binary formats.
+    pub descriptor_line: bool,
+    /// Pad each CSV and TSV field to the width of its column, so the output
+    /// lines up for reading. Fields are still only quoted when necessary, in
+    /// which case the padding is inside the quotes.
+    pub pretty_csv: bool,
+    /// End every CSV and TSV row, including the header, with the delimiter
+    pub trailing_delimiter: bool,
+    /// `(column, ascending)` pairs of the columns the results are sorted by.
+    /// [`PrintFormat::Table`] appends `▲` or `▼` to the header of each.
+    pub sort_indicators: Vec<(String, bool)>,
+    /// In [`Self::describe`] mode, also show up to this many of the most
+    /// frequent non-null values of each column with their counts. `0`
+    /// disables the summary.
+    pub top_k: usize,
+    /// Append the `[min..max]` range of the values of each numeric column to
+    /// its [`PrintFormat::Table`] header. When streaming, the ranges are only
+    /// shown if every row arrives before the header is printed, as the header
+    /// cannot include the rows that follow it.
+    pub header_range: bool,
+    /// The number of spaces between [`PrintFormat::FixedWidth`] columns
+    pub column_gap: usize,
+    /// Pad the last [`PrintFormat::FixedWidth`] column to its width, so every
+    /// line has the same length
+    pub pad_right: bool,
+    /// Print the output of any format base64 encoded, after a `base64:<format>`
+    /// line, so it can be embedded in text-only channels. Parquet, which is
+    /// written to [`Self::output_path`], cannot be wrapped
+    pub base64_wrap: bool,
+    /// The maximum length of the base64 lines, or `None` for a single line
+    pub base64_line_width: Option<usize>,
+    /// Stop computing the [`PrintFormat::Table`] column widths once no width
+    /// has grown for this many consecutive rows.
+    ///
+    /// This makes computing the widths of a large preview cheap when the
+    /// values have similar widths, but wider values after the scan stops are
+    /// truncated to fit.
+    pub width_stable_after: Option<usize>,
+    /// A label printed in `=== <label> ===` lines before and after the
+    /// output, so the results of a script can be told apart. The lines are
+    /// commented for formats that support comments.
+    pub section_label: Option<String>,
+    /// Wrap column names wider than their column onto several lines instead
+    /// of widening the column. Names are broken after `_` and at spaces
+    /// where possible, so the column is at least as wide as the widest word.
+    pub wrap_headers: bool,
+    /// Prefix [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] string values
+    /// starting with `=`, `+`, `-` or `@` with `'`, so spreadsheets such as
+    /// Google Sheets do not evaluate them as formulas (CSV injection)
+    pub sanitize_formulas: bool,
+    /// Renderers of the non-null values of the columns of a data type. The
+    /// rendered strings are used to compute widths and are printed instead
+    /// of the formatted values. Not applied to JSON output.
+    pub type_renderers: HashMap<DataType, CellRenderer>,
+    /// The minimum width of a [`PrintFormat::Table`] column, so a column with
+    /// an empty name and only empty values does not render as `||`
+    pub min_column_width: usize,
+    /// Prefix each [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] row with a
+    /// `batch_index` column holding the index of its batch among the batches
+    /// passed to [`PrintFormat::print_with_options`]
+    pub batch_index_column: bool,
+    /// Replace the [`Self::truncation_rows`] dotted lines marking that
+    /// [`PrintFormat::Table`] rows were omitted with a single
+    /// `… and N more rows` line
+    pub collapsed_truncation: bool,
+    /// How NaN and infinite float values, which JSON cannot represent, are
+    /// printed in [`PrintFormat::Json`] and [`PrintFormat::NdJson`] output
+    pub json_nan_policy: JsonNanPolicy,
+    /// Where warnings about the output, such as coerced values, are written
+    pub warnings: WarningSink,
+    /// The `class` attribute of the [`PrintFormat::Html`] `<table>` element
+    pub html_table_class: Option<String>,
+    /// The severity written as a leading `level=` field of every
+    /// [`PrintFormat::Logfmt`] row
+    pub logfmt_level: Option<String>,
+    /// The file [`PrintFormat::Parquet`] output is written to instead of the
+    /// writer passed to [`PrintFormat::print_with_options`]
+    pub output_path: Option<PathBuf>,
+    /// Print the header line of [`PrintFormat::Csv`] and [`PrintFormat::Tsv`]
+    /// output even when there are no rows, so the columns are always named.
+    /// Set by default, unset for no output at all.
+    pub header_on_empty: bool,
+    /// The maximum width of [`PrintFormat::Table`] output, such as the width
+    /// of the terminal. Columns are dropped, lowest
+    /// [`Self::column_priorities`] first, until the table fits
+    pub max_table_width: Option<usize>,
+    /// The priority of each named column when columns are dropped to fit
+    /// [`Self::max_table_width`], where unlisted columns have priority `0`
+    pub column_priorities: HashMap<String, i32>,
+    /// End [`PrintFormat::NdJson`] output with a newline after the last
+    /// record, or strip it for consumers that reject a trailing empty line
+    pub ndjson_trailing_newline: bool,
+    /// The field metadata key whose value, when present, is the header of
+    /// the column instead of the field name, such as `display_name`
+    pub header_from_metadata: Option<String>,
+    /// The maximum display width of a [`PrintFormat::Table`],
+    /// [`PrintFormat::FixedWidth`] or [`PrintFormat::Markdown`] column, where
+    /// longer values are cut short and end with `…`
+    pub max_column_width: Option<usize>,
+    /// Leave the keys of null values out of [`PrintFormat::Ini`] sections,
+    /// rather than printing them with an empty value
+    pub ini_omit_nulls: bool,
+    /// Print the [`PrintFormat::Table`] rows matching a predicate with an
+    /// ANSI style
+    pub highlight: Option<RowHighlight>,
+    /// How `Utf8` and `LargeUtf8` values that are not valid UTF-8, such as
+    /// arrays built unsafely or received over FFI, are printed
+    pub invalid_utf8: InvalidUtf8Policy,
+    /// The character encoding of [`PrintFormat::Csv`] and [`PrintFormat::Tsv`]
+    /// output
+    pub encoding: OutputEncoding,
+    /// Instead of the rows, print the min, median, 95th percentile and max
+    /// display width of the values of each column, regardless of the format,
+    /// to help choose truncation limits
+    pub width_histogram: bool,
+    /// Escape tabs and line breaks in [`PrintFormat::Tsv`] string values as
+    /// `\t`, `\n` and `\r`, so every line is one row with one field per
+    /// column. When `false` such values are quoted.
+    pub tsv_escape: bool,
+    /// With [`Self::tsv_escape`], also escape backslashes as `\\`, so the
+    /// escapes can be reversed, as by readers that unescape such as
+    /// PostgreSQL's `COPY`
+    pub tsv_escape_backslashes: bool,
+    /// Instead of dropping the [`PrintFormat::Table`] columns that do not fit
+    /// in [`Self::max_table_width`], wrap them into further tables printed
+    /// below, each starting with the first column, so every row stays
+    /// labelled
+    pub sticky_first_column: bool,
+    /// The character [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] fields are
+    /// quoted with, which must be ASCII
+    pub csv_quote: char,
+    /// Which [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] fields are quoted
+    pub csv_quote_style: QuoteStyle,
+    /// The SQL that produced the batches, printed in comment lines before
+    /// the output so it can be reproduced. Not printed for formats without
+    /// comments.
+    pub source_sql: Option<String>,
+    /// Prefix [`PrintFormat::Table`] rows with a `#` column numbering them
+    /// from `1`, continuing across batches. When rows are streamed through
+    /// [`OutputStreamState`] without a [`MaxRows::Limited`], the column is
+    /// sized for six digit numbers, and the rows numbered past that are
+    /// wider than the rest of the table.
+    pub row_numbers: bool,
+    /// Stop writing and return `Ok` when the writer fails with
+    /// [`std::io::ErrorKind::BrokenPipe`], as when the output is piped to
+    /// `head` and it exits early, rather than returning the error
+    pub ignore_broken_pipe: bool,
+    /// Print a `(N rows)` line under [`PrintFormat::Table`] output, or
+    /// `(showing N of M rows)` when rows were omitted because of `maxrows`
+    pub row_count_footer: bool,
+    /// The [`PrintFormat::Table`] columns whose non-null values are colored
+    /// by value, so equal values always have the same color
+    pub categorical_color_columns: HashSet<String>,
+    /// The ANSI SGR parameters of the colors values of
+    /// [`Self::categorical_color_columns`] are hashed to
+    pub categorical_palette: Vec<String>,
+    /// The width [`PrintFormat::Table`] output is shrunk to fit, such as the
+    /// width of the terminal. The widest columns are narrowed first, so
+    /// narrow columns stay intact, and values cut short end with `…`.
+    pub fit_width: Option<usize>,
+    /// Give each [`PrintFormat::Html`] `<td>` a `class` from its value, for
+    /// styling with a stylesheet: `null` for nulls, `num` for numbers and
+    /// `str` for strings
+    pub html_css_classes: bool,
+    /// Print [`PrintFormat::Table`] headers in bold and null values dimmed
+    /// with ANSI codes, which do not affect the column widths
+    pub colorize: bool,
+    /// With [`Self::colorize`], the ANSI SGR parameters of the color of
+    /// numeric [`PrintFormat::Table`] values, such as `"36"` for cyan
+    pub numeric_color: Option<String>,
+    /// Print empty strings as `null` in [`PrintFormat::Json`],
+    /// [`PrintFormat::NdJson`] and [`PrintFormat::JsonPretty`] output, for
+    /// sources that use empty strings to mean null. Null values, which are
+    /// otherwise left out of JSON objects, are then printed too.
+    pub empty_string_as_null: bool,
+    /// Group the [`PrintFormat::Table`] columns whose names share the part
+    /// before this delimiter, such as `sales.q1` and `sales.q2`, under a
+    /// header row spanning the group, above the rest of their names
+    pub group_headers_delimiter: Option<char>,
+    /// Print a line after the [`PrintFormat::Table`] output explaining the
+    /// markers the values were printed with, such as
+    /// `NULL = null, … = truncated`, listing only the markers used
+    pub print_legend: bool,
+    /// The characters the [`PrintFormat::Table`] borders are drawn with
+    pub border_style: BorderStyle,
+    /// Print [`PrintFormat::Tsv`] output for loading into R or pandas, see
+    /// [`DataSciencePreset`]
+    pub data_science_preset: Option<DataSciencePreset>,
+    /// The number of dotted lines marking that [`PrintFormat::Table`] rows
+    /// were omitted, at least `1`
+    pub truncation_rows: usize,
+    /// Skip a batch streamed through [`OutputStreamState`] that is identical
+    /// to the batch before it, such as one emitted again by a retried
+    /// operator, with a warning
+    pub dedup_consecutive_batches: bool,
+    /// Print the [`PrintFormat::Table`] header again, between separator
+    /// borders, after every this many rows, so it stays in view when
+    /// scrolling through long output
+    pub header_interval: Option<usize>,
+    /// How the [`PrintFormat::Table`] headers and values are aligned
+    pub alignment_policy: AlignmentPolicy,
+    /// The alignment of the header and values of [`PrintFormat::Table`]
+    /// columns, overriding [`Self::alignment_policy`], by column name or by
+    /// the index of the column from `0`
+    pub column_alignments: HashMap<String, Alignment>,
+    /// End [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] output with a
+    /// `# N rows` comment line counting the rows written, such as for scripts
+    /// to check. Strict CSV parsers reject the line.
+    pub row_count_comment: bool,
+    /// Start UTF-8 [`PrintFormat::Csv`] and [`PrintFormat::Tsv`] output with
+    /// a byte order mark, which Excel needs to read non-ASCII characters
+    pub csv_bom: bool,
+    /// The number of decimal places floating point and decimal values are
+    /// printed with, rather than as many as needed. Not applied to JSON
+    /// output.
+    pub float_precision: Option<usize>,
+    /// Print floating point and decimal values in scientific notation, such
+    /// as `3.14e2`. Not applied to JSON output.
+    pub float_scientific: bool,
+    /// Insert this separator between the groups of three digits of integer
+    /// and decimal [`PrintFormat::Table`] values, such as `1,234,567`. The
+    /// other formats keep machine readable numbers.
+    pub group_digits: Option<char>,
+    /// How binary values are printed. Not applied to JSON output.
+    pub binary_encoding: BinaryEncoding,
+    /// The most rows printed
+    pub maxrows: MaxRows,
+    /// Print the header of the formats where it is optional, such as
+    /// [`PrintFormat::Csv`]
+    pub with_header: bool,
+    /// How values are formatted, such as the string nulls are printed as
+    pub format_options: FormatOptions,
+    /// Print only the columns of these names, in this order, whatever the
+    /// format, leaving out the other columns of the batches
+    pub display_columns: Option<Vec<String>>,
+    /// Replace an existing file at [`Self::output_path`] rather than fail,
+    /// see [`create_output_file`]
+    pub force_overwrite: bool,
 }
 
-fn print_batches_with_sep<W: std::io::Write>(
-    writer: &mut W,
-    batches: &[RecordBatch],
-    delimiter: u8,
-    with_header: bool,
-) -> Result<()> {
-    let builder = WriterBuilder::new()
-        .with_header(with_header)
-        .with_delimiter(delimiter);
-    let mut csv_writer = builder.build(writer);
+impl Default for PrintBatchesOptions {
+    fn default() -> Self {
+        Self {
+            normalize_unicode: None,
+            pivot: None,
+            fixed_widths: None,
+            describe: false,
+            json_ascii_escape: false,
+            table_header: true,
+            bool_tokens: None,
+            descriptor_line: false,
+            pretty_csv: false,
+            trailing_delimiter: false,
+            sort_indicators: vec![],
+            top_k: 0,
+            header_range: false,
+            column_gap: 1,
+            pad_right: false,
+            base64_wrap: false,
+            base64_line_width: None,
+            width_stable_after: None,
+            section_label: None,
+            wrap_headers: false,
+            sanitize_formulas: false,
+            type_renderers: HashMap::new(),
+            min_column_width: 1,
+            batch_index_column: false,
+            collapsed_truncation: false,
+            json_nan_policy: JsonNanPolicy::default(),
+            warnings: WarningSink::default(),
+            html_table_class: None,
+            logfmt_level: None,
+            output_path: None,
+            header_on_empty: true,
+            max_table_width: None,
+            column_priorities: HashMap::new(),
+            ndjson_trailing_newline: true,
+            header_from_metadata: None,
+            max_column_width: None,
+            ini_omit_nulls: false,
+            highlight: None,
+            invalid_utf8: InvalidUtf8Policy::default(),
+            encoding: OutputEncoding::default(),
+            width_histogram: false,
+            tsv_escape: true,
+            tsv_escape_backslashes: false,
+            sticky_first_column: false,
+            csv_quote: '"',
+            csv_quote_style: QuoteStyle::default(),
+            source_sql: None,
+            row_numbers: false,
+            ignore_broken_pipe: true,
+            row_count_footer: false,
+            categorical_color_columns: HashSet::new(),
+            categorical_palette: [
+                "31", "32", "33", "34", "35", "36", "91", "92", "93", "94", "95", "96",
+            ]
+            .map(String::from)
+            .to_vec(),
+            fit_width: None,
+            html_css_classes: false,
+            colorize: false,
+            numeric_color: None,
+            empty_string_as_null: false,
+            group_headers_delimiter: None,
+            print_legend: false,
+            border_style: BorderStyle::default(),
+            data_science_preset: None,
+            truncation_rows: 3,
+            dedup_consecutive_batches: false,
+            header_interval: None,
+            alignment_policy: AlignmentPolicy::default(),
+            column_alignments: HashMap::new(),
+            row_count_comment: false,
+            csv_bom: false,
+            float_precision: None,
+            float_scientific: false,
+            group_digits: None,
+            binary_encoding: BinaryEncoding::default(),
+            maxrows: MaxRows::Unlimited,
+            with_header: true,
+            format_options: FormatOptions::default(),
+            display_columns: None,
+            force_overwrite: false,
+        }
+    }
+}
 
-    for batch in batches {
-        csv_writer.write(batch)?;
+impl PrintBatchesOptions {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    Ok(())
-}
+    /// set the unicode normalization applied to string values
+    pub fn with_normalize_unicode(mut self, form: Option<NormalizationForm>) -> Self {
+        self.normalize_unicode = form;
+        self
+    }
 
-fn keep_only_maxrows(s: &str, maxrows: usize) -> String {
-    let lines: Vec<String> = s.lines().map(String::from).collect();
+    /// set the column roles used by [`PrintFormat::Pivot`]
+    pub fn with_pivot(mut self, pivot: Option<PivotColumns>) -> Self {
+        self.pivot = pivot;
+        self
+    }
 
-    assert!(lines.len() >= maxrows + 4); // 4 lines for top and bottom border
+    /// set the exact widths of the Table columns
+    pub fn with_fixed_widths(mut self, fixed_widths: Option<Vec<usize>>) -> Self {
+        self.fixed_widths = fixed_widths;
+        self
+    }
 
-    let last_line = &lines[lines.len() - 1]; // bottom border line
+    /// set whether to describe the columns instead of printing the rows
+    pub fn with_describe(mut self, describe: bool) -> Self {
+        self.describe = describe;
+        self
+    }
 
-    let spaces = last_line.len().saturating_sub(4);
-    let dotted_line = format!("| .{:<spaces$}|", "", spaces = spaces);
+    /// set whether to escape non-ASCII characters in JSON output
+    pub fn with_json_ascii_escape(mut self, json_ascii_escape: bool) -> Self {
+        self.json_ascii_escape = json_ascii_escape;
+        self
+    }
 
-    let mut result = lines[0..(maxrows + 3)].to_vec(); // Keep top border and `maxrows` lines
-    result.extend(vec![dotted_line; 3]); // Append ... lines
-    result.push(last_line.clone());
+    /// set whether Table output includes the column names
+    pub fn with_table_header(mut self, table_header: bool) -> Self {
+        self.table_header = table_header;
+        self
+    }
 
-    result.join("\n")
-}
+    /// set the `(true, false, null)` tokens printed for boolean values
+    pub fn with_bool_tokens(
+        mut self,
+        bool_tokens: Option<(String, String, String)>,
+    ) -> Self {
+        self.bool_tokens = bool_tokens;
+        self
+    }
 
-fn format_batches_with_maxrows<W: std::io::Write>(
-    writer: &mut W,
-    batches: &[RecordBatch],
-    maxrows: MaxRows,
-    format_options: &FormatOptions,
-) -> Result<()> {
-    let options: arrow::util::display::FormatOptions = format_options.try_into()?;
+    /// set whether to print a line describing the format and schema first
+    pub fn with_descriptor_line(mut self, descriptor_line: bool) -> Self {
+        self.descriptor_line = descriptor_line;
+        self
+    }
 
-    match maxrows {
-        MaxRows::Limited(maxrows) => {
-            // Filter batches to meet the maxrows condition
-            let mut filtered_batches = Vec::new();
-            let mut row_count: usize = 0;
-            let mut over_limit = false;
-            for batch in batches {
-                if row_count + batch.num_rows() > maxrows {
-                    // If adding this batch exceeds maxrows, slice the batch
-                    let limit = maxrows - row_count;
-                    let sliced_batch = batch.slice(0, limit);
-                    filtered_batches.push(sliced_batch);
-                    over_limit = true;
-                    break;
-                } else {
-                    filtered_batches.push(batch.clone());
-                    row_count += batch.num_rows();
-                }
-            }
+    /// set whether to pad CSV and TSV fields into aligned columns
+    pub fn with_pretty_csv(mut self, pretty_csv: bool) -> Self {
+        self.pretty_csv = pretty_csv;
+        self
+    }
 
-            let formatted =
-                pretty_format_batches_with_options(&filtered_batches, &options)?;
-            if over_limit {
-                let mut formatted_str = format!("{formatted}");
-                formatted_str = keep_only_maxrows(&formatted_str, maxrows);
-                writeln!(writer, "{formatted_str}")?;
-            } else {
-                writeln!(writer, "{formatted}")?;
-            }
+    /// set whether to end every CSV and TSV row with the delimiter
+    pub fn with_trailing_delimiter(mut self, trailing_delimiter: bool) -> Self {
+        self.trailing_delimiter = trailing_delimiter;
+        self
+    }
+
+    /// set the `(column, ascending)` pairs marked as sorted in Table headers
+    pub fn with_sort_indicators(mut self, sort_indicators: Vec<(String, bool)>) -> Self {
+        self.sort_indicators = sort_indicators;
+        self
+    }
+
+    /// set how many of the most frequent values describe mode shows
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// set whether Table headers show the range of numeric columns
+    pub fn with_header_range(mut self, header_range: bool) -> Self {
+        self.header_range = header_range;
+        self
+    }
+
+    /// set the number of spaces between FixedWidth columns
+    pub fn with_column_gap(mut self, column_gap: usize) -> Self {
+        self.column_gap = column_gap;
+        self
+    }
+
+    /// set whether the last FixedWidth column is padded to its width
+    pub fn with_pad_right(mut self, pad_right: bool) -> Self {
+        self.pad_right = pad_right;
+        self
+    }
+
+    /// set whether to print the output base64 encoded
+    pub fn with_base64_wrap(mut self, base64_wrap: bool) -> Self {
+        self.base64_wrap = base64_wrap;
+        self
+    }
+
+    /// set the maximum length of the base64 lines
+    pub fn with_base64_line_width(mut self, base64_line_width: Option<usize>) -> Self {
+        self.base64_line_width = base64_line_width;
+        self
+    }
+
+    /// set after how many rows without growth the column widths are final
+    pub fn with_width_stable_after(mut self, width_stable_after: Option<usize>) -> Self {
+        self.width_stable_after = width_stable_after;
+        self
+    }
+
+    /// set the label printed before and after the output
+    pub fn with_section_label(mut self, section_label: Option<String>) -> Self {
+        self.section_label = section_label;
+        self
+    }
+
+    /// set whether long column names wrap onto several lines
+    pub fn with_wrap_headers(mut self, wrap_headers: bool) -> Self {
+        self.wrap_headers = wrap_headers;
+        self
+    }
+
+    /// set whether to neutralize CSV and TSV values spreadsheets read as formulas
+    pub fn with_sanitize_formulas(mut self, sanitize_formulas: bool) -> Self {
+        self.sanitize_formulas = sanitize_formulas;
+        self
+    }
+
+    /// set the minimum width of a Table column
+    pub fn with_min_column_width(mut self, min_column_width: usize) -> Self {
+        self.min_column_width = min_column_width;
+        self
+    }
+
+    /// set whether CSV and TSV rows start with the index of their batch
+    pub fn with_batch_index_column(mut self, batch_index_column: bool) -> Self {
+        self.batch_index_column = batch_index_column;
+        self
+    }
+
+    /// set whether omitted Table rows are counted on a single line
+    pub fn with_collapsed_truncation(mut self, collapsed_truncation: bool) -> Self {
+        self.collapsed_truncation = collapsed_truncation;
+        self
+    }
+
+    /// set how NaN and infinite values are printed in JSON output
+    pub fn with_json_nan_policy(mut self, json_nan_policy: JsonNanPolicy) -> Self {
+        self.json_nan_policy = json_nan_policy;
+        self
+    }
+
+    /// set where warnings about the output are written
+    pub fn with_warnings(mut self, warnings: WarningSink) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// set the class attribute of the Html table
+    pub fn with_html_table_class(mut self, html_table_class: impl Into<String>) -> Self {
+        self.html_table_class = Some(html_table_class.into());
+        self
+    }
+
+    /// set the severity of every Logfmt row
+    pub fn with_logfmt_level(mut self, logfmt_level: impl Into<String>) -> Self {
+        self.logfmt_level = Some(logfmt_level.into());
+        self
+    }
+
+    /// set the file Parquet output is written to
+    pub fn with_output_path(mut self, output_path: Option<PathBuf>) -> Self {
+        self.output_path = output_path;
+        self
+    }
+
+    /// set whether CSV and TSV output has a header when there are no rows
+    pub fn with_header_on_empty(mut self, header_on_empty: bool) -> Self {
+        self.header_on_empty = header_on_empty;
+        self
+    }
+
+    /// set the maximum width of Table output
+    pub fn with_max_table_width(mut self, max_table_width: Option<usize>) -> Self {
+        self.max_table_width = max_table_width;
+        self
+    }
+
+    /// set the priority of the columns dropped to fit the maximum table width
+    pub fn with_column_priorities(
+        mut self,
+        column_priorities: HashMap<String, i32>,
+    ) -> Self {
+        self.column_priorities = column_priorities;
+        self
+    }
+
+    /// set whether NdJson output ends with a newline
+    pub fn with_ndjson_trailing_newline(mut self, ndjson_trailing_newline: bool) -> Self {
+        self.ndjson_trailing_newline = ndjson_trailing_newline;
+        self
+    }
+
+    /// set the field metadata key holding the header of each column
+    pub fn with_header_from_metadata(
+        mut self,
+        header_from_metadata: Option<String>,
+    ) -> Self {
+        self.header_from_metadata = header_from_metadata;
+        self
+    }
+
+    /// set the maximum width of a Table column
+    pub fn with_max_column_width(mut self, max_column_width: Option<usize>) -> Self {
+        self.max_column_width = max_column_width;
+        self
+    }
+
+    /// set whether null values are left out of Ini sections
+    pub fn with_ini_omit_nulls(mut self, ini_omit_nulls: bool) -> Self {
+        self.ini_omit_nulls = ini_omit_nulls;
+        self
+    }
+
+    /// add a renderer for the values of the columns of `data_type`, replacing
+    /// any renderer registered for it before
+    pub fn with_type_renderer(
+        mut self,
+        data_type: DataType,
+        renderer: impl Fn(&dyn Array, usize) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.type_renderers
+            .insert(data_type, CellRenderer::new(renderer));
+        self
+    }
+
+    /// set the rows printed with an ANSI style
+    pub fn with_highlight(mut self, highlight: Option<RowHighlight>) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    /// set how string values that are not valid UTF-8 are printed
+    pub fn with_invalid_utf8(mut self, invalid_utf8: InvalidUtf8Policy) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// set the character encoding of CSV and TSV output
+    pub fn with_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// set whether to print the distribution of column value widths
+    pub fn with_width_histogram(mut self, width_histogram: bool) -> Self {
+        self.width_histogram = width_histogram;
+        self
+    }
+
+    /// set whether TSV string values have tabs and line breaks escaped
+    pub fn with_tsv_escape(mut self, tsv_escape: bool) -> Self {
+        self.tsv_escape = tsv_escape;
+        self
+    }
+
+    /// set whether escaped TSV string values have backslashes escaped too
+    pub fn with_tsv_escape_backslashes(mut self, tsv_escape_backslashes: bool) -> Self {
+        self.tsv_escape_backslashes = tsv_escape_backslashes;
+        self
+    }
+
+    /// set whether columns wider than the table wrap after the first column
+    pub fn with_sticky_first_column(mut self, sticky_first_column: bool) -> Self {
+        self.sticky_first_column = sticky_first_column;
+        self
+    }
+
+    /// set the character CSV and TSV fields are quoted with
+    pub fn with_csv_quote(mut self, csv_quote: char) -> Self {
+        self.csv_quote = csv_quote;
+        self
+    }
+
+    /// set which CSV and TSV fields are quoted
+    pub fn with_csv_quote_style(mut self, csv_quote_style: QuoteStyle) -> Self {
+        self.csv_quote_style = csv_quote_style;
+        self
+    }
+
+    /// set the SQL printed as a comment before the output
+    pub fn with_source_sql(mut self, source_sql: Option<String>) -> Self {
+        self.source_sql = source_sql;
+        self
+    }
+
+    /// set whether Table rows are prefixed with their row number
+    pub fn with_row_numbers(mut self, row_numbers: bool) -> Self {
+        self.row_numbers = row_numbers;
+        self
+    }
+
+    /// set whether a closed output ends printing without an error
+    pub fn with_ignore_broken_pipe(mut self, ignore_broken_pipe: bool) -> Self {
+        self.ignore_broken_pipe = ignore_broken_pipe;
+        self
+    }
+
+    /// set whether Table output ends with a line counting the rows
+    pub fn with_row_count_footer(mut self, row_count_footer: bool) -> Self {
+        self.row_count_footer = row_count_footer;
+        self
+    }
+
+    /// set the Table columns whose values are colored by value
+    pub fn with_categorical_color_columns(
+        mut self,
+        categorical_color_columns: HashSet<String>,
+    ) -> Self {
+        self.categorical_color_columns = categorical_color_columns;
+        self
+    }
+
+    /// set the colors of categorical values
+    pub fn with_categorical_palette(mut self, categorical_palette: Vec<String>) -> Self {
+        self.categorical_palette = categorical_palette;
+        self
+    }
+
+    /// set the width Table output is shrunk to fit
+    pub fn with_fit_width(mut self, fit_width: Option<usize>) -> Self {
+        self.fit_width = fit_width;
+        self
+    }
+
+    /// set whether HTML cells have a class from their value
+    pub fn with_html_css_classes(mut self, html_css_classes: bool) -> Self {
+        self.html_css_classes = html_css_classes;
+        self
+    }
+
+    /// set whether Table headers and nulls are styled with ANSI codes
+    pub fn with_colorize(mut self, colorize: bool) -> Self {
+        self.colorize = colorize;
+        self
+    }
+
+    /// set the color of numeric Table values when colorizing
+    pub fn with_numeric_color(mut self, numeric_color: Option<String>) -> Self {
+        self.numeric_color = numeric_color;
+        self
+    }
+
+    /// set whether JSON output prints empty strings as null
+    pub fn with_empty_string_as_null(mut self, empty_string_as_null: bool) -> Self {
+        self.empty_string_as_null = empty_string_as_null;
+        self
+    }
+
+    /// set the delimiter grouping the Table columns under spanning headers
+    pub fn with_group_headers_delimiter(
+        mut self,
+        group_headers_delimiter: Option<char>,
+    ) -> Self {
+        self.group_headers_delimiter = group_headers_delimiter;
+        self
+    }
+
+    /// set whether to print a legend of the markers used in Table output
+    pub fn with_print_legend(mut self, print_legend: bool) -> Self {
+        self.print_legend = print_legend;
+        self
+    }
+
+    /// set the characters Table borders are drawn with
+    pub fn with_border_style(mut self, border_style: BorderStyle) -> Self {
+        self.border_style = border_style;
+        self
+    }
+    /// The options the [`DataSciencePreset`] stands for once its comment line
+    /// is printed: escaping keeps line breaks within values from ending
+    /// rows, and nulls are empty
+    fn without_data_science_preset(&self) -> Self {
+        Self {
+            data_science_preset: None,
+            tsv_escape: true,
+            format_options: FormatOptions {
+                null: String::new(),
+                ..self.format_options.clone()
+            },
+            ..self.clone()
         }
-        MaxRows::Unlimited => {
-            let formatted = pretty_format_batches_with_options(batches, &options)?;
-            writeln!(writer, "{formatted}")?;
+    }
+
+    /// set the preset of TSV output for R and pandas
+    pub fn with_data_science_preset(
+        mut self,
+        data_science_preset: Option<DataSciencePreset>,
+    ) -> Self {
+        self.data_science_preset = data_science_preset;
+        self
+    }
+
+    /// set the number of dotted lines marking omitted Table rows
+    pub fn with_truncation_rows(mut self, truncation_rows: usize) -> Self {
+        self.truncation_rows = truncation_rows;
+        self
+    }
+
+    /// set whether streamed batches identical to the previous one are skipped
+    pub fn with_dedup_consecutive_batches(
+        mut self,
+        dedup_consecutive_batches: bool,
+    ) -> Self {
+        self.dedup_consecutive_batches = dedup_consecutive_batches;
+        self
+    }
+
+    /// set the number of rows after which the Table header is printed again
+    pub fn with_header_interval(mut self, header_interval: Option<usize>) -> Self {
+        self.header_interval = header_interval;
+        self
+    }
+
+    /// set how Table headers and values are aligned
+    pub fn with_alignment_policy(mut self, alignment_policy: AlignmentPolicy) -> Self {
+        self.alignment_policy = alignment_policy;
+        self
+    }
+
+    /// set the alignment of Table columns by name or index
+    pub fn with_column_alignments(
+        mut self,
+        column_alignments: HashMap<String, Alignment>,
+    ) -> Self {
+        self.column_alignments = column_alignments;
+        self
+    }
+
+    /// set whether CSV and TSV output ends with a row count comment
+    pub fn with_row_count_comment(mut self, row_count_comment: bool) -> Self {
+        self.row_count_comment = row_count_comment;
+        self
+    }
+
+    /// set whether CSV and TSV output starts with a UTF-8 byte order mark
+    pub fn with_csv_bom(mut self, csv_bom: bool) -> Self {
+        self.csv_bom = csv_bom;
+        self
+    }
+
+    /// set the number of decimal places of floating point and decimal values
+    pub fn with_float_precision(mut self, float_precision: Option<usize>) -> Self {
+        self.float_precision = float_precision;
+        self
+    }
+
+    /// set whether floating point and decimal values use scientific notation
+    pub fn with_float_scientific(mut self, float_scientific: bool) -> Self {
+        self.float_scientific = float_scientific;
+        self
+    }
+
+    /// set the separator of the digit groups of Table integers and decimals
+    pub fn with_group_digits(mut self, group_digits: Option<char>) -> Self {
+        self.group_digits = group_digits;
+        self
+    }
+
+    /// set how binary values are printed
+    pub fn with_binary_encoding(mut self, binary_encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = binary_encoding;
+        self
+    }
+
+    /// set the most rows printed
+    pub fn with_maxrows(mut self, maxrows: MaxRows) -> Self {
+        self.maxrows = maxrows;
+        self
+    }
+
+    /// set whether the header of the formats where it is optional is printed
+    pub fn with_header(mut self, with_header: bool) -> Self {
+        self.with_header = with_header;
+        self
+    }
+
+    /// set how values are formatted
+    pub fn with_format_options(mut self, format_options: FormatOptions) -> Self {
+        self.format_options = format_options;
+        self
+    }
+
+    /// set the names of the only columns to print
+    pub fn with_display_columns(mut self, display_columns: Option<Vec<String>>) -> Self {
+        self.display_columns = display_columns;
+        self
+    }
+
+    /// set whether an existing output file is replaced
+    pub fn with_force_overwrite(mut self, force_overwrite: bool) -> Self {
+        self.force_overwrite = force_overwrite;
+        self
+    }
+}
+
+/// Column roles for printing a cross-tab with [`PrintFormat::Pivot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PivotColumns {
+    /// Column whose distinct values become the rows of the cross-tab
+    pub row: String,
+    /// Column whose distinct values become the columns of the cross-tab
+    pub column: String,
+    /// Column whose values fill the cells of the cross-tab
+    pub value: String,
+}
+
+impl PivotColumns {
+    pub fn new(
+        row: impl Into<String>,
+        column: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        Self {
+            row: row.into(),
+            column: column.into(),
+            value: value.into(),
         }
     }
+}
+
+/// [`PrintFormat::Tsv`] output that R's `read.delim` and pandas' `read_csv`
+/// load as is: a first `# col types: ...` comment line lists the data type of
+/// each column, nulls are empty fields whatever the format options say, and
+/// values are escaped so every row ends with a single LF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataSciencePreset {
+    /// The character starting the comment line, which the reader is told to
+    /// skip with R's `comment.char` or pandas' `comment`
+    pub comment_char: char,
+}
+
+impl Default for DataSciencePreset {
+    fn default() -> Self {
+        Self { comment_char: '#' }
+    }
+}
 
+/// Print the `# col types: ...` comment line of [`DataSciencePreset`]
+fn print_column_types<W: std::io::Write>(
+    writer: &mut W,
+    preset: &DataSciencePreset,
+    schema: &SchemaRef,
+) -> Result<()> {
+    let types: Vec<String> = schema
+        .fields()
+        .iter()
+        .map(|field| field.data_type().to_string())
+        .collect();
+    writeln!(
+        writer,
+        "{} col types: {}",
+        preset.comment_char,
+        types.join("\t")
+    )?;
     Ok(())
 }
 
-impl PrintFormat {
-    /// Print the batches to a writer using the specified format
-    pub fn print_batches<W: std::io::Write>(
-        &self,
-        writer: &mut W,
-        schema: SchemaRef,
-        batches: &[RecordBatch],
-        maxrows: MaxRows,
-        with_header: bool,
-        format_options: &FormatOptions,
-    ) -> Result<()> {
-        // filter out any empty batches
-        let batches: Vec<_> = batches
-            .iter()
-            .filter(|b| b.num_rows() > 0)
-            .cloned()
-            .collect();
-        if batches.is_empty() {
-            return self.print_empty(writer, schema, format_options);
-        }
+/// Whether the row at the index of the batch, as it is printed, is highlighted
+pub type RowPredicate = Arc<dyn Fn(&RecordBatch, usize) -> bool + Send + Sync>;
 
-        match self {
-            Self::Csv | Self::Automatic => {
-                print_batches_with_sep(writer, &batches, b',', with_header)
-            }
-            Self::Tsv => print_batches_with_sep(writer, &batches, b'\t', with_header),
-            Self::Table => {
-                if maxrows == MaxRows::Limited(0) {
-                    return Ok(());
-                }
-                format_batches_with_maxrows(writer, &batches, maxrows, format_options)
-            }
-            Self::Json => batches_to_json!(ArrayWriter, writer, &batches),
-            Self::NdJson => batches_to_json!(LineDelimitedWriter, writer, &batches),
+/// Rows of a [`PrintFormat::Table`] printed with an ANSI style, such as to
+/// draw attention to anomalies
+#[derive(Clone)]
+pub struct RowHighlight {
+    /// Whether the row at the index of the batch, as it is printed, is
+    /// highlighted
+    pub predicate: RowPredicate,
+    /// The ANSI SGR parameters of the style, such as `"1;41"` for bold text
+    /// on a red background
+    pub style: String,
+}
+
+impl RowHighlight {
+    pub fn new(
+        predicate: impl Fn(&RecordBatch, usize) -> bool + Send + Sync + 'static,
+        style: impl Into<String>,
+    ) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+            style: style.into(),
         }
     }
+}
 
-    /// Print when the result batches contain no rows
-    fn print_empty<W: std::io::Write>(
-        &self,
-        writer: &mut W,
-        schema: SchemaRef,
-        format_options: &FormatOptions,
-    ) -> Result<()> {
-        match self {
-            // Print column headers for Table format
-            Self::Table if !schema.fields().is_empty() => {
-                let format_options: arrow::util::display::FormatOptions =
-                    format_options.try_into()?;
+impl std::fmt::Debug for RowHighlight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RowHighlight")
+            .field("style", &self.style)
+            .finish_non_exhaustive()
+    }
+}
 
-                let empty_batch = RecordBatch::new_empty(schema);
-                let formatted =
-                    pretty_format_batches_with_options(&[empty_batch], &format_options)?;
-                writeln!(writer, "{formatted}")?;
-            }
-            _ => {}
-        }
-        Ok(())
+/// Renders the value at the row of the array, see [`CellRenderer`]
+pub type RenderFn = Arc<dyn Fn(&dyn Array, usize) -> String + Send + Sync>;
+
+/// Renders the value at a row of an array, see
+/// [`PrintBatchesOptions::type_renderers`]
+#[derive(Clone)]
+pub struct CellRenderer(RenderFn);
+
+impl CellRenderer {
+    pub fn new(
+        renderer: impl Fn(&dyn Array, usize) -> String + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(renderer))
+    }
+
+    /// Render the value at `row` of `array`
+    pub fn render(&self, array: &dyn Array, row: usize) -> String {
+        (self.0)(array, row)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::Arc;
+impl std::fmt::Debug for CellRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CellRenderer").finish_non_exhaustive()
+    }
+}
 
-    use arrow::array::Int32Array;
-    use arrow::datatypes::{DataType, Field, Schema};
-    use insta::{allow_duplicates, assert_snapshot};
+/// The column [`PrintFormat::write_partitioned`] splits the output by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionColumn {
+    /// The name of the column
+    pub name: String,
+    /// Whether the column is kept in the written files. Its value is already
+    /// in the file name, so it is dropped by default.
+    pub keep: bool,
+}
+
+impl PartitionColumn {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            keep: false,
+        }
+    }
+
+    /// set whether the column is kept in the written files
+    pub fn with_keep(mut self, keep: bool) -> Self {
+        self.keep = keep;
+        self
+    }
+}
+
+/// The file name stem for rows with the partition `value`, with characters
+/// that are not safe in file names replaced by `_`
+fn partition_file_stem(value: Option<&str>) -> String {
+    match value {
+        None => "__null__".to_string(),
+        Some("") => "__empty__".to_string(),
+        Some(value) => value
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect(),
+    }
+}
+
+/// How NaN and infinite float values are printed in JSON output
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum JsonNanPolicy {
+    /// Print them as `null`
+    #[default]
+    Null,
+    /// Print them as `null`, and warn once with the number of values
+    NullWithWarning,
+    /// Return an error
+    Error,
+}
+
+/// Where warnings about the output are written
+#[derive(Debug, Default, Clone)]
+pub enum WarningSink {
+    #[default]
+    Stderr,
+    /// Collect the warnings, such as to show them elsewhere
+    Collect(Arc<Mutex<Vec<String>>>),
+}
+
+impl WarningSink {
+    fn warn(&self, message: String) {
+        match self {
+            Self::Stderr => eprintln!("Warning: {message}"),
+            Self::Collect(warnings) => warnings.lock().push(message),
+        }
+    }
+}
+
+/// Replace the NaN and infinite values of `array` with nulls, returning the
+/// number of replaced values
+fn non_finite_to_null<T: ArrowPrimitiveType>(
+    array: &PrimitiveArray<T>,
+    is_finite: impl Fn(T::Native) -> bool,
+) -> (usize, ArrayRef) {
+    let count = array.iter().flatten().filter(|v| !is_finite(*v)).count();
+    let array: PrimitiveArray<T> = array.unary_opt(|v| is_finite(v).then_some(v));
+    (count, Arc::new(array))
+}
+
+/// Replace the NaN and infinite values of the float columns of `batch` with
+/// nulls, returning the number of replaced values
+fn non_finite_to_null_batch(batch: &RecordBatch) -> Result<(usize, RecordBatch)> {
+    let mut count = 0;
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| {
+            let (replaced, column) = match column.data_type() {
+                DataType::Float16 => {
+                    non_finite_to_null(column.as_primitive::<Float16Type>(), |v| {
+                        v.is_finite()
+                    })
+                }
+                DataType::Float32 => non_finite_to_null(
+                    column.as_primitive::<Float32Type>(),
+                    f32::is_finite,
+                ),
+                DataType::Float64 => non_finite_to_null(
+                    column.as_primitive::<Float64Type>(),
+                    f64::is_finite,
+                ),
+                _ => (0, Arc::clone(column)),
+            };
+            count += replaced;
+            column
+        })
+        .collect();
+    Ok((count, RecordBatch::try_new(batch.schema(), columns)?))
+}
+
+/// How binary values are printed
+#[derive(Debug, Default, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
+pub enum BinaryEncoding {
+    /// As arrow formats them
+    #[default]
+    Default,
+    /// Two lowercase hexadecimal digits per byte
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+}
+
+/// Which fields of separated values output are quoted
+#[derive(Debug, Default, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
+pub enum QuoteStyle {
+    /// Quote only the fields containing the delimiter, the quote character
+    /// or a line break
+    #[default]
+    Necessary,
+    /// Quote every field, including the header and empty fields
+    Always,
+}
+
+/// The characters [`PrintFormat::Table`] borders are drawn with
+#[derive(Debug, Default, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
+pub enum BorderStyle {
+    /// `+`, `-` and `|`
+    #[default]
+    Ascii,
+    /// Unicode box-drawing characters, such as `┌─┬─┐` and `│`, which are
+    /// as wide as their ASCII counterparts
+    Unicode,
+}
+
+/// The horizontal borders of a [`PrintFormat::Table`]
+#[derive(Debug, Clone, Copy)]
+enum BorderLine {
+    Top,
+    /// Below the header
+    Separator,
+    Bottom,
+}
+
+impl BorderStyle {
+    /// The left end, line, junction and right end characters of `line`
+    fn horizontal(&self, line: BorderLine) -> [char; 4] {
+        match (self, line) {
+            (Self::Ascii, _) => ['+', '-', '+', '+'],
+            (Self::Unicode, BorderLine::Top) => ['┌', '─', '┬', '┐'],
+            (Self::Unicode, BorderLine::Separator) => ['├', '─', '┼', '┤'],
+            (Self::Unicode, BorderLine::Bottom) => ['└', '─', '┴', '┘'],
+        }
+    }
+
+    /// The character between columns and at both ends of a row
+    fn vertical(&self) -> char {
+        match self {
+            Self::Ascii => '|',
+            Self::Unicode => '│',
+        }
+    }
+}
+
+/// The character encoding of the output
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// UTF-16 little endian starting with a byte order mark, as some Windows
+    /// applications expect for CSV
+    Utf16Le,
+}
+
+/// The UTF-8 encoding of the byte order mark `U+FEFF`
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Encode `output` as UTF-16 little endian, starting with a byte order mark
+fn encode_utf16le(output: &str) -> Vec<u8> {
+    std::iter::once(0xFEFF_u16)
+        .chain(output.encode_utf16())
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// How string values that are not valid UTF-8 are printed
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum InvalidUtf8Policy {
+    /// Replace each invalid sequence with `U+FFFD`
+    #[default]
+    Lossy,
+    /// Replace each invalid byte with `\xNN`
+    HexEscape,
+    /// Return an error
+    Error,
+}
+
+impl InvalidUtf8Policy {
+    fn repair(&self, bytes: &[u8]) -> Result<String> {
+        match self {
+            Self::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            Self::HexEscape => {
+                let mut repaired = String::with_capacity(bytes.len());
+                for chunk in bytes.utf8_chunks() {
+                    repaired.push_str(chunk.valid());
+                    for byte in chunk.invalid() {
+                        repaired.push_str(&format!("\\x{byte:02x}"));
+                    }
+                }
+                Ok(repaired)
+            }
+            Self::Error => exec_err!("Invalid UTF-8 in string value {bytes:?}"),
+        }
+    }
+}
+
+/// Repair the values of `array` that are not valid UTF-8 according to
+/// `policy`, or return `None` if every value is valid
+fn repair_utf8_array<O: OffsetSizeTrait>(
+    array: &GenericStringArray<O>,
+    policy: InvalidUtf8Policy,
+) -> Result<Option<ArrayRef>> {
+    let offsets = array.value_offsets();
+    let data = array.value_data();
+    let bytes = |idx: usize| &data[offsets[idx].as_usize()..offsets[idx + 1].as_usize()];
+    if (0..array.len())
+        .all(|idx| array.is_null(idx) || std::str::from_utf8(bytes(idx)).is_ok())
+    {
+        return Ok(None);
+    }
+
+    let repaired = (0..array.len())
+        .map(|idx| {
+            if array.is_null(idx) {
+                return Ok(None);
+            }
+            match std::str::from_utf8(bytes(idx)) {
+                Ok(value) => Ok(Some(value.to_string())),
+                Err(_) => policy.repair(bytes(idx)).map(Some),
+            }
+        })
+        .collect::<Result<GenericStringArray<O>>>()?;
+    Ok(Some(Arc::new(repaired)))
+}
+
+/// Repair the `Utf8` and `LargeUtf8` values of `batch` that are not valid
+/// UTF-8 according to `policy`
+fn repair_utf8_batch(
+    batch: &RecordBatch,
+    policy: InvalidUtf8Policy,
+) -> Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| {
+            let repaired = match column.data_type() {
+                DataType::Utf8 => repair_utf8_array(column.as_string::<i32>(), policy)?,
+                DataType::LargeUtf8 => {
+                    repair_utf8_array(column.as_string::<i64>(), policy)?
+                }
+                _ => None,
+            };
+            Ok(repaired.unwrap_or_else(|| Arc::clone(column)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// Unicode normalization form applied to string values
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NormalizationForm {
+    /// Canonical decomposition followed by canonical composition
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+}
+
+impl NormalizationForm {
+    fn normalize(&self, s: &str) -> String {
+        match self {
+            Self::Nfc => s.nfc().collect(),
+            Self::Nfd => s.nfd().collect(),
+        }
+    }
+}
+
+/// Apply the unicode normalization `form` to every string column of `batch`
+fn normalize_batch(batch: &RecordBatch, form: NormalizationForm) -> Result<RecordBatch> {
+    map_string_columns(batch, |v| form.normalize(v))
+}
+
+/// Prefix the string values of `batch` that spreadsheets would evaluate as
+/// formulas with `'`
+fn sanitize_formulas_batch(batch: &RecordBatch) -> Result<RecordBatch> {
+    map_string_columns(batch, |v| {
+        if v.starts_with(['=', '+', '-', '@']) {
+            format!("'{v}")
+        } else {
+            v.to_string()
+        }
+    })
+}
+
+/// Replace every value of the string columns of `batch` with `f(value)`
+fn map_string_columns(
+    batch: &RecordBatch,
+    f: impl Fn(&str) -> String,
+) -> Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|column| -> ArrayRef {
+            match column.data_type() {
+                DataType::Utf8 => Arc::new(
+                    column
+                        .as_string::<i32>()
+                        .iter()
+                        .map(|v| v.map(&f))
+                        .collect::<StringArray>(),
+                ),
+                DataType::LargeUtf8 => Arc::new(
+                    column
+                        .as_string::<i64>()
+                        .iter()
+                        .map(|v| v.map(&f))
+                        .collect::<LargeStringArray>(),
+                ),
+                DataType::Utf8View => Arc::new(
+                    column
+                        .as_string_view()
+                        .iter()
+                        .map(|v| v.map(&f))
+                        .collect::<StringViewArray>(),
+                ),
+                _ => Arc::clone(column),
+            }
+        })
+        .collect();
+
+    Ok(RecordBatch::try_new(batch.schema(), columns)?)
+}
+
+/// Transform the batches into a single cross-tab batch.
+///
+/// The distinct values of the `row` and `column` columns, in the order they
+/// are first seen, become the rows and columns of the result. Each cell holds
+/// the formatted `value` of the matching input row, or null if there is none.
+/// It is an error for more than one input row to map to the same cell.
+fn pivot_batches(
+    batches: &[RecordBatch],
+    schema: &SchemaRef,
+    pivot: &PivotColumns,
+    format_options: &FormatOptions,
+) -> Result<RecordBatch> {
+    let options: arrow::util::display::FormatOptions = format_options.try_into()?;
+    let row_idx = schema.index_of(&pivot.row)?;
+    let column_idx = schema.index_of(&pivot.column)?;
+    let value_idx = schema.index_of(&pivot.value)?;
+
+    // nulls are kept apart from the values printed as the null string, such
+    // as a `NULL` string
+    let mut row_keys: Vec<Option<String>> = vec![];
+    let mut column_keys: Vec<Option<String>> = vec![];
+    let mut row_positions: HashMap<Option<String>, usize> = HashMap::new();
+    let mut column_positions: HashMap<Option<String>, usize> = HashMap::new();
+    let mut cells: HashMap<(usize, usize), Option<String>> = HashMap::new();
+
+    for batch in batches {
+        let rows = formatted_values(batch.column(row_idx).as_ref(), &options)?;
+        let columns = formatted_values(batch.column(column_idx).as_ref(), &options)?;
+        let values = formatted_values(batch.column(value_idx).as_ref(), &options)?;
+
+        for ((row_key, column_key), value) in rows.into_iter().zip(columns).zip(values) {
+            let row = *row_positions.entry(row_key.clone()).or_insert_with(|| {
+                row_keys.push(row_key.clone());
+                row_keys.len() - 1
+            });
+            let column =
+                *column_positions
+                    .entry(column_key.clone())
+                    .or_insert_with(|| {
+                        column_keys.push(column_key.clone());
+                        column_keys.len() - 1
+                    });
+
+            if cells.insert((row, column), value).is_some() {
+                let null = &format_options.null;
+                return exec_err!(
+                    "Cannot pivot: multiple values of '{}' for {} = {} and {} = {}",
+                    pivot.value,
+                    pivot.row,
+                    row_key.as_deref().unwrap_or(null),
+                    pivot.column,
+                    column_key.as_deref().unwrap_or(null)
+                );
+            }
+        }
+    }
+
+    // a null column key is named as the null string, yet remains a column
+    // of its own
+    let mut fields = vec![Field::new(&pivot.row, DataType::Utf8, true)];
+    fields.extend(column_keys.iter().map(|key| {
+        Field::new(
+            key.as_deref().unwrap_or(&format_options.null),
+            DataType::Utf8,
+            true,
+        )
+    }));
+    let num_rows = row_keys.len();
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(StringArray::from(row_keys))];
+    arrays.extend((0..column_keys.len()).map(|column| -> ArrayRef {
+        Arc::new(
+            (0..num_rows)
+                .map(|row| cells.get(&(row, column)).cloned().flatten())
+                .collect::<StringArray>(),
+        )
+    }));
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// The values of `array` formatted with `options`, with `None` for the nulls
+fn formatted_values(
+    array: &dyn Array,
+    options: &arrow::util::display::FormatOptions,
+) -> Result<Vec<Option<String>>> {
+    let formatter = ArrayFormatter::try_new(array, options)?;
+    // a `Null` array has no null buffer, but every value is null
+    let nulls = array.logical_nulls();
+    Ok((0..array.len())
+        .map(|i| match &nulls {
+            Some(nulls) if nulls.is_null(i) => None,
+            _ => Some(formatter.value(i).to_string()),
+        })
+        .collect())
+}
+
+/// Replace each boolean column of `batch` with a string column of the
+/// `(true, false, null)` tokens
+fn bool_tokens_batch(
+    batch: &RecordBatch,
+    (true_token, false_token, null_token): &(String, String, String),
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| field.data_type() == &DataType::Boolean)
+    {
+        return Ok(batch.clone());
+    }
+
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| match column.data_type() {
+            DataType::Boolean => {
+                let tokens: StringArray = column
+                    .as_boolean()
+                    .iter()
+                    .map(|value| match value {
+                        Some(true) => Some(true_token),
+                        Some(false) => Some(false_token),
+                        None => Some(null_token),
+                    })
+                    .collect();
+                let field = field.as_ref().clone().with_data_type(DataType::Utf8);
+                (field, Arc::new(tokens) as ArrayRef)
+            }
+            _ => (field.as_ref().clone(), Arc::clone(column)),
+        })
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Format `value` with `precision` decimal places, if set, and in scientific
+/// notation if `scientific`
+fn format_float<T: std::fmt::Display + std::fmt::LowerExp>(
+    value: T,
+    precision: Option<usize>,
+    scientific: bool,
+) -> String {
+    match (precision, scientific) {
+        (Some(precision), true) => format!("{value:.precision$e}"),
+        (Some(precision), false) => format!("{value:.precision$}"),
+        (None, true) => format!("{value:e}"),
+        (None, false) => value.to_string(),
+    }
+}
+
+/// Round the values of a decimal `column` to `precision` decimal places,
+/// widening its precision to keep the integer digits. The decimal places
+/// are fewer when the integer digits leave no room for them, and values
+/// that still do not fit are an error rather than null.
+fn round_decimal(column: &ArrayRef, precision: usize) -> Result<ArrayRef> {
+    let (digits, scale, max_digits) = match column.data_type() {
+        DataType::Decimal128(digits, scale) => {
+            (*digits, *scale, DECIMAL128_MAX_PRECISION)
+        }
+        DataType::Decimal256(digits, scale) => {
+            (*digits, *scale, DECIMAL256_MAX_PRECISION)
+        }
+        _ => return Ok(Arc::clone(column)),
+    };
+    let integer_digits = (digits as i16 - scale as i16).max(1);
+    let rounded_scale = (precision as i16)
+        .min(max_digits as i16 - integer_digits)
+        .max(0) as i8;
+    let rounded_digits =
+        (integer_digits + rounded_scale as i16).clamp(1, max_digits as i16) as u8;
+    let data_type = match column.data_type() {
+        DataType::Decimal128(..) => DataType::Decimal128(rounded_digits, rounded_scale),
+        _ => DataType::Decimal256(rounded_digits, rounded_scale),
+    };
+    let cast_options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    Ok(cast_with_options(column, &data_type, &cast_options)?)
+}
+
+/// Replace each floating point and decimal column of `batch` with a string
+/// column of its values formatted by [`format_float`]. Decimals are rounded
+/// exactly, and only converted to `f64` for scientific notation.
+fn format_floats_batch(
+    batch: &RecordBatch,
+    precision: Option<usize>,
+    scientific: bool,
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| -> Result<(Field, ArrayRef)> {
+            let format = |value| format_float(value, precision, scientific);
+            let formatted: StringArray = match column.data_type() {
+                DataType::Float16 => column
+                    .as_primitive::<Float16Type>()
+                    .iter()
+                    .map(|v| v.map(|v| format(f64::from(v))))
+                    .collect(),
+                DataType::Float32 => column
+                    .as_primitive::<Float32Type>()
+                    .iter()
+                    .map(|v| v.map(|v| format_float(v, precision, scientific)))
+                    .collect(),
+                DataType::Float64 => column
+                    .as_primitive::<Float64Type>()
+                    .iter()
+                    .map(|v| v.map(format))
+                    .collect(),
+                DataType::Decimal128(..) | DataType::Decimal256(..) if scientific => {
+                    cast(column, &DataType::Float64)?
+                        .as_primitive::<Float64Type>()
+                        .iter()
+                        .map(|v| v.map(format))
+                        .collect()
+                }
+                DataType::Decimal128(..) | DataType::Decimal256(..) => {
+                    let Some(precision) = precision else {
+                        return Ok((field.as_ref().clone(), Arc::clone(column)));
+                    };
+                    let rounded = round_decimal(column, precision)?;
+                    (0..rounded.len())
+                        .map(|row| {
+                            rounded
+                                .is_valid(row)
+                                .then(|| array_value_to_string(&rounded, row))
+                                .transpose()
+                        })
+                        .collect::<std::result::Result<_, _>>()?
+                }
+                _ => return Ok((field.as_ref().clone(), Arc::clone(column))),
+            };
+            let field = field.as_ref().clone().with_data_type(DataType::Utf8);
+            Ok((field, Arc::new(formatted)))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Replace each binary column of `batch` with a string column of its values
+/// encoded as `encoding`
+fn encode_binary_batch(
+    batch: &RecordBatch,
+    encoding: BinaryEncoding,
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    if encoding == BinaryEncoding::Default {
+        return Ok(batch.clone());
+    }
+
+    let encode = |bytes: &[u8]| match encoding {
+        BinaryEncoding::Base64 => STANDARD.encode(bytes),
+        _ => bytes.iter().map(|byte| format!("{byte:02x}")).collect(),
+    };
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            let encoded: StringArray = match column.data_type() {
+                DataType::Binary => column
+                    .as_binary::<i32>()
+                    .iter()
+                    .map(|v| v.map(encode))
+                    .collect(),
+                DataType::LargeBinary => column
+                    .as_binary::<i64>()
+                    .iter()
+                    .map(|v| v.map(encode))
+                    .collect(),
+                DataType::BinaryView => column
+                    .as_binary_view()
+                    .iter()
+                    .map(|v| v.map(encode))
+                    .collect(),
+                DataType::FixedSizeBinary(_) => column
+                    .as_fixed_size_binary()
+                    .iter()
+                    .map(|v| v.map(encode))
+                    .collect(),
+                _ => return (field.as_ref().clone(), Arc::clone(column)),
+            };
+            let field = field.as_ref().clone().with_data_type(DataType::Utf8);
+            (field, Arc::new(encoded) as ArrayRef)
+        })
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Insert `separator` between the groups of three digits of the integer part
+/// of a formatted number, such as `-1234567.89` to `-1,234,567.89`
+fn group_digits(value: &str, separator: char) -> String {
+    let unsigned = value.trim_start_matches(['-', '+']);
+    let sign = &value[..value.len() - unsigned.len()];
+    let integer_len = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (integer, rest) = unsigned.split_at(integer_len);
+    let mut grouped = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str(rest);
+    grouped
+}
+
+/// Replace each column of `batch` flagged in `numeric` with a string column
+/// of its formatted values with their digits grouped by [`group_digits`]
+fn group_digits_batch(
+    batch: &RecordBatch,
+    numeric: &[bool],
+    separator: char,
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .zip(numeric)
+        .map(|((field, column), numeric)| -> Result<(Field, ArrayRef)> {
+            if !numeric {
+                return Ok((field.as_ref().clone(), Arc::clone(column)));
+            }
+            let grouped: StringArray = (0..column.len())
+                .map(|row| {
+                    column
+                        .is_valid(row)
+                        .then(|| array_value_to_string(column, row))
+                        .transpose()
+                        .map(|value| value.map(|value| group_digits(&value, separator)))
+                })
+                .collect::<std::result::Result<_, _>>()?;
+            let field = field.as_ref().clone().with_data_type(DataType::Utf8);
+            Ok((field, Arc::new(grouped)))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Replace the empty values of the string columns of `batch` with nulls,
+/// making the string fields nullable
+fn empty_strings_to_null_batch(batch: &RecordBatch) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| {
+            fn non_empty(value: Option<&str>) -> Option<&str> {
+                value.filter(|value| !value.is_empty())
+            }
+            let column: ArrayRef = match column.data_type() {
+                DataType::Utf8 => Arc::new(
+                    column
+                        .as_string::<i32>()
+                        .iter()
+                        .map(non_empty)
+                        .collect::<StringArray>(),
+                ),
+                DataType::LargeUtf8 => Arc::new(
+                    column
+                        .as_string::<i64>()
+                        .iter()
+                        .map(non_empty)
+                        .collect::<LargeStringArray>(),
+                ),
+                DataType::Utf8View => Arc::new(
+                    column
+                        .as_string_view()
+                        .iter()
+                        .map(non_empty)
+                        .collect::<StringViewArray>(),
+                ),
+                _ => return (field.as_ref().clone(), Arc::clone(column)),
+            };
+            (field.as_ref().clone().with_nullable(true), column)
+        })
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Replace each column of `batch` with a renderer for its data type with a
+/// string column of the rendered values
+fn render_types_batch(
+    batch: &RecordBatch,
+    renderers: &HashMap<DataType, CellRenderer>,
+) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    if !schema
+        .fields()
+        .iter()
+        .any(|field| renderers.contains_key(field.data_type()))
+    {
+        return Ok(batch.clone());
+    }
+
+    let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| match renderers.get(column.data_type()) {
+            Some(renderer) => {
+                let rendered: StringArray = (0..column.len())
+                    .map(|row| {
+                        column
+                            .is_valid(row)
+                            .then(|| renderer.render(column.as_ref(), row))
+                    })
+                    .collect();
+                let field = field.as_ref().clone().with_data_type(DataType::Utf8);
+                (field, Arc::new(rendered) as ArrayRef)
+            }
+            None => (field.as_ref().clone(), Arc::clone(column)),
+        })
+        .unzip();
+
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Return `schema` with a leading `batch_index` column
+fn batch_index_schema(schema: &SchemaRef) -> SchemaRef {
+    let fields: Vec<Field> =
+        std::iter::once(Field::new("batch_index", DataType::UInt64, false))
+            .chain(schema.fields().iter().map(|field| field.as_ref().clone()))
+            .collect();
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Prefix `batch` with a `batch_index` column holding `index`
+fn with_batch_index(batch: &RecordBatch, index: usize) -> Result<RecordBatch> {
+    let index: ArrayRef =
+        Arc::new(UInt64Array::from(vec![index as u64; batch.num_rows()]));
+    let columns: Vec<ArrayRef> = std::iter::once(index)
+        .chain(batch.columns().iter().cloned())
+        .collect();
+    Ok(RecordBatch::try_new(
+        batch_index_schema(&batch.schema()),
+        columns,
+    )?)
+}
+
+/// Prefix `schema` with the `#` row number column
+fn row_number_schema(schema: &SchemaRef) -> SchemaRef {
+    let fields: Vec<Field> = std::iter::once(Field::new("#", DataType::UInt64, false))
+        .chain(schema.fields().iter().map(|field| field.as_ref().clone()))
+        .collect();
+    Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Prefix `batch` with a `#` column numbering its rows from `first`
+fn with_row_numbers(batch: &RecordBatch, first: usize) -> Result<RecordBatch> {
+    let numbers: ArrayRef = Arc::new(UInt64Array::from_iter_values(
+        (first..first + batch.num_rows()).map(|number| number as u64),
+    ));
+    let columns: Vec<ArrayRef> = std::iter::once(numbers)
+        .chain(batch.columns().iter().cloned())
+        .collect();
+    Ok(RecordBatch::try_new(
+        row_number_schema(&batch.schema()),
+        columns,
+    )?)
+}
+
+macro_rules! batches_to_json {
+    ($WRITER: ident, $writer: expr, $batches: expr) => {{
+        batches_to_json!($WRITER, $writer, $batches, false)
+    }};
+    ($WRITER: ident, $writer: expr, $batches: expr, $explicit_nulls: expr) => {{
+        {
+            if !$batches.is_empty() {
+                let mut json_writer: $WRITER<_> = JsonWriterBuilder::new()
+                    .with_explicit_nulls($explicit_nulls)
+                    .build(&mut *$writer);
+                for batch in $batches {
+                    json_writer.write(batch)?;
+                }
+                json_writer.finish()?;
+                json_finish!($WRITER, $writer);
+            }
+        }
+        Ok(()) as Result<()>
+    }};
+}
+
+macro_rules! json_finish {
+    (ArrayWriter, $writer: expr) => {{
+        writeln!($writer)?;
+    }};
+    (LineDelimitedWriter, $writer: expr) => {{}};
+}
+
+/// Indent compact `json` by two spaces per level, with each array element
+/// and object field on its own line. Empty arrays and objects stay `[]` and
+/// `{}`.
+fn indent_json(json: &str) -> String {
+    const INDENT: &str = "  ";
+    let mut indented = String::with_capacity(json.len() * 2);
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = json.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            indented.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                indented.push(c);
+            }
+            '{' | '[' => {
+                indented.push(c);
+                if matches!(chars.peek(), Some('}' | ']')) {
+                    indented.push(chars.next().unwrap());
+                } else {
+                    depth += 1;
+                    indented.push('\n');
+                    indented.push_str(&INDENT.repeat(depth));
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                indented.push('\n');
+                indented.push_str(&INDENT.repeat(depth));
+                indented.push(c);
+            }
+            ',' => {
+                indented.push(c);
+                indented.push('\n');
+                indented.push_str(&INDENT.repeat(depth));
+            }
+            ':' => indented.push_str(": "),
+            _ => indented.push(c),
+        }
+    }
+    indented
+}
+
+/// Escape every non-ASCII character of `json` as `\uXXXX`, using surrogate
+/// pairs for characters outside the basic multilingual plane
+fn escape_json_non_ascii(json: &str) -> String {
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            let mut units = [0_u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                escaped.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    escaped
+}
+
+fn print_batches_with_sep<W: std::io::Write>(
+    writer: &mut W,
+    batches: &[RecordBatch],
+    delimiter: u8,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    if options.trailing_delimiter {
+        let mut buffer: Vec<u8> = vec![];
+        let options = PrintBatchesOptions {
+            trailing_delimiter: false,
+            ..options.clone()
+        };
+        print_batches_with_sep(&mut buffer, batches, delimiter, &options)?;
+        writer.write_all(&append_trailing_delimiter(
+            &buffer,
+            delimiter,
+            options.csv_quote as u8,
+        ))?;
+        return Ok(());
+    }
+
+    if !options.csv_quote.is_ascii() {
+        return exec_err!(
+            "The quote character must be ASCII, but got '{}'",
+            options.csv_quote
+        );
+    }
+
+    if options.pretty_csv || options.csv_quote_style == QuoteStyle::Always {
+        let rows = quoted_rows(batches, delimiter as char, options)?;
+        return print_separated_rows(
+            writer,
+            rows,
+            delimiter as char,
+            options.pretty_csv,
+            options.csv_quote,
+        );
+    }
+
+    let mut csv_writer = csv_writer_builder(delimiter, options).build(writer);
+
+    for batch in batches {
+        csv_writer.write(batch)?;
+    }
+
+    Ok(())
+}
+
+/// The arrow CSV writer of values separated by `delimiter` with the quote and
+/// the null string of the options
+fn csv_writer_builder(delimiter: u8, options: &PrintBatchesOptions) -> WriterBuilder {
+    WriterBuilder::new()
+        .with_header(options.with_header)
+        .with_delimiter(delimiter)
+        .with_quote(options.csv_quote as u8)
+        .with_null(options.format_options.null.clone())
+}
+
+/// Append `delimiter` to every record of the separated values in `buffer`.
+/// Line breaks within fields quoted with `quote` do not end a record.
+fn append_trailing_delimiter(buffer: &[u8], delimiter: u8, quote: u8) -> Vec<u8> {
+    let mut output = Vec::with_capacity(buffer.len());
+    let mut in_quotes = false;
+    for &byte in buffer {
+        match byte {
+            // an escaped quote toggles twice
+            _ if byte == quote => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => output.push(delimiter),
+            _ => {}
+        }
+        output.push(byte);
+    }
+    output
+}
+
+/// Escape a TSV field, so it contains no tab or line break. With
+/// `backslashes`, backslashes are escaped too, so the escapes can be
+/// reversed.
+fn escape_tsv(field: &str, backslashes: bool) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' if backslashes => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quote a separated value field with `quote` if `style` requires it, where
+/// quotes within the field are doubled
+fn quote_field(field: &str, delimiter: char, quote: char, style: QuoteStyle) -> String {
+    let needs_quotes = match style {
+        QuoteStyle::Necessary => field.contains([delimiter, quote, '\n', '\r']),
+        QuoteStyle::Always => true,
+    };
+    if needs_quotes {
+        let doubled = field.replace(quote, &format!("{quote}{quote}"));
+        format!("{quote}{doubled}{quote}")
+    } else {
+        field.to_string()
+    }
+}
+
+/// Format the batches as rows of separated value fields, quoted as set by
+/// [`PrintBatchesOptions::csv_quote_style`], starting with the header if
+/// [`PrintBatchesOptions::with_header`] is set
+fn quoted_rows(
+    batches: &[RecordBatch],
+    delimiter: char,
+    options: &PrintBatchesOptions,
+) -> Result<Vec<Vec<String>>> {
+    let Some(schema) = batches.first().map(|batch| batch.schema()) else {
+        return Ok(vec![]);
+    };
+    let quote = |field: &str| {
+        quote_field(field, delimiter, options.csv_quote, options.csv_quote_style)
+    };
+
+    let mut rows: Vec<Vec<String>> = vec![];
+    if options.with_header {
+        rows.push(
+            schema
+                .fields()
+                .iter()
+                .map(|field| quote(field.name()))
+                .collect(),
+        );
+    }
+    for batch in batches {
+        for row in format_rows(batch, &options.format_options)? {
+            rows.push(row.iter().map(|cell| quote(cell)).collect());
+        }
+    }
+    Ok(rows)
+}
+
+/// Print rows of quoted separated value fields. If `align` is set every field
+/// is padded to the width of its column, where padding goes inside the quotes
+/// of quoted fields, so the output remains valid.
+fn print_separated_rows<W: std::io::Write>(
+    writer: &mut W,
+    rows: Vec<Vec<String>>,
+    delimiter: char,
+    align: bool,
+    quote: char,
+) -> Result<()> {
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    if align {
+        for row in &rows {
+            for (width, field) in widths.iter_mut().zip(row) {
+                *width = (*width).max(line_width(field));
+            }
+        }
+    }
+
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(field, width)| {
+                let padding = " ".repeat(width.saturating_sub(line_width(field)));
+                match field.strip_suffix(quote) {
+                    Some(quoted) if field.len() > 1 && field.starts_with(quote) => {
+                        format!("{quoted}{padding}{quote}")
+                    }
+                    _ => format!("{field}{padding}"),
+                }
+            })
+            .collect();
+        writeln!(writer, "{}", fields.join(&delimiter.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Print the batches as newline delimited JSON to `writer`, and a
+/// [JSON Schema] (draft-07) describing each record to `schema_writer`.
+///
+/// The schema is derived from `schema` and is written even when there are no
+/// rows, so downstream consumers can always validate the data.
+///
+/// [JSON Schema]: https://json-schema.org/draft-07/schema
+pub fn print_ndjson_with_schema<W: std::io::Write, S: std::io::Write>(
+    writer: &mut W,
+    schema_writer: &mut S,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<()> {
+    let batches: Vec<_> = batches
+        .iter()
+        .filter(|b| b.num_rows() > 0)
+        .cloned()
+        .collect();
+    batches_to_json!(LineDelimitedWriter, writer, &batches)?;
+
+    let mut json_schema = json_schema_object(schema.fields());
+    json_schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    writeln!(schema_writer, "{json_schema}")?;
+    Ok(())
+}
+
+/// Print the batches as a [`PrintFormat::Table`] to `human_writer` and as
+/// [`PrintFormat::NdJson`] to `machine_writer` in a single pass, such as to
+/// show results interactively while also logging them.
+///
+/// [`PrintBatchesOptions::maxrows`] only limits the Table: every row is
+/// written to `machine_writer`.
+pub fn print_dual<H: std::io::Write, M: std::io::Write>(
+    human_writer: &mut H,
+    machine_writer: &mut M,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    // the NdJson options apply to `machine_writer`, which the output path
+    // does not turn into another format
+    let json_options = PrintBatchesOptions {
+        maxrows: MaxRows::Unlimited,
+        output_path: None,
+        ..options.clone()
+    };
+    let mut table_state = OutputStreamState::new(
+        human_writer,
+        Arc::clone(&schema),
+        options.maxrows,
+        &options.format_options,
+    )
+    .with_options(options.clone());
+    let mut json_state = OutputStreamState::new(
+        machine_writer,
+        schema,
+        MaxRows::Unlimited,
+        &options.format_options,
+    )
+    .with_format(PrintFormat::NdJson)
+    .with_options(json_options);
+    for batch in batches {
+        table_state.process_batch(batch)?;
+        json_state.process_batch(batch)?;
+    }
+    json_state.finish()?;
+    table_state.finish()
+}
+
+/// An output of [`print_multi`]
+pub struct PrintTarget<'a> {
+    pub writer: &'a mut dyn std::io::Write,
+    pub format: PrintFormat,
+    /// The maximum number of rows printed to this target, whatever the format
+    pub maxrows: MaxRows,
+}
+
+impl<'a> PrintTarget<'a> {
+    pub fn new(
+        writer: &'a mut dyn std::io::Write,
+        format: PrintFormat,
+        maxrows: MaxRows,
+    ) -> Self {
+        Self {
+            writer,
+            format,
+            maxrows,
+        }
+    }
+}
+
+/// The state of a [`PrintTarget`] while [`print_multi`] iterates the batches
+enum TargetState<'a> {
+    /// [`PrintFormat::Table`] rows are printed as the batches arrive
+    Table(Box<OutputStreamState<&'a mut dyn std::io::Write>>),
+    /// The batches of other formats, sliced to the `maxrows` of the target,
+    /// are printed once all have arrived
+    Batches {
+        target: PrintTarget<'a>,
+        batches: Vec<RecordBatch>,
+        rows: usize,
+    },
+}
+
+/// Print the batches to several targets, each with its own format and row
+/// limit, in a single pass over the batches, such as a Table preview of the
+/// first rows along with a CSV dump of all of them.
+///
+/// The batches are sliced rather than copied, and a Table truncated by its
+/// `maxrows` ends with the usual dotted lines, while other formats just stop.
+/// The other print options, including the header, apply to every target.
+pub fn print_multi(
+    targets: Vec<PrintTarget<'_>>,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let options = PrintBatchesOptions {
+        format_options: format_options.clone(),
+        ..options.clone()
+    };
+    let mut states: Vec<TargetState> = targets
+        .into_iter()
+        .map(|target| match target.format {
+            PrintFormat::Table => TargetState::Table(Box::new(
+                OutputStreamState::new(
+                    target.writer,
+                    Arc::clone(&schema),
+                    target.maxrows,
+                    format_options,
+                )
+                .with_options(options.clone().with_maxrows(target.maxrows)),
+            )),
+            _ => TargetState::Batches {
+                target,
+                batches: vec![],
+                rows: 0,
+            },
+        })
+        .collect();
+
+    for batch in batches.iter().filter(|b| b.num_rows() > 0) {
+        for state in &mut states {
+            match state {
+                TargetState::Table(table_state) => table_state.process_batch(batch)?,
+                TargetState::Batches {
+                    target,
+                    batches,
+                    rows,
+                } => {
+                    let remaining = match target.maxrows {
+                        MaxRows::Limited(maxrows) => maxrows.saturating_sub(*rows),
+                        MaxRows::Unlimited => usize::MAX,
+                    };
+                    let kept = batch.num_rows().min(remaining);
+                    if kept > 0 {
+                        batches.push(batch.slice(0, kept));
+                        *rows += kept;
+                    }
+                }
+            }
+        }
+    }
+
+    for state in states {
+        match state {
+            TargetState::Table(table_state) => table_state.finish()?,
+            // the batches are already sliced to the `maxrows` of the target
+            TargetState::Batches {
+                mut target,
+                batches,
+                ..
+            } => target.format.print_with_options(
+                &mut target.writer,
+                Arc::clone(&schema),
+                &batches,
+                &options.clone().with_maxrows(MaxRows::Unlimited),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Instead of printing the batches, return them as a single batch of
+/// `schema`, such as to register the result of a query as a table for the
+/// next one.
+///
+/// Every batch must have the columns of `schema`, by name and data type, but
+/// may differ in metadata, which is replaced by that of `schema`.
+pub fn collect_for_reuse(
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<Vec<RecordBatch>> {
+    let batches = batches
+        .iter()
+        .map(|batch| {
+            let batch_schema = batch.schema();
+            let compatible = batch_schema.fields().len() == schema.fields().len()
+                && batch_schema.fields().iter().zip(schema.fields()).all(
+                    |(batch_field, field)| {
+                        batch_field.name() == field.name()
+                            && batch_field.data_type() == field.data_type()
+                    },
+                );
+            if !compatible {
+                return exec_err!(
+                    "Expected batches with the schema {schema}, but got {batch_schema}"
+                );
+            }
+            Ok(RecordBatch::try_new(
+                Arc::clone(&schema),
+                batch.columns().to_vec(),
+            )?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(vec![concat_batches(&schema, &batches)?])
+}
+
+/// Return the JSON Schema of an object with the given fields.
+///
+/// Null values are omitted by the arrow JSON writer, so only non-nullable
+/// fields are listed as `required`.
+fn json_schema_object(fields: &Fields) -> Value {
+    let properties: serde_json::Map<String, Value> = fields
+        .iter()
+        .map(|f| (f.name().clone(), json_schema_type(f.data_type())))
+        .collect();
+    let required: Vec<&String> = fields
+        .iter()
+        .filter(|f| !f.is_nullable())
+        .map(|f| f.name())
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Return the JSON Schema of the values the arrow JSON writer produces for
+/// `data_type`
+fn json_schema_type(data_type: &DataType) -> Value {
+    match data_type {
+        DataType::Null => json!({ "type": "null" }),
+        DataType::Boolean => json!({ "type": "boolean" }),
+        dt if dt.is_integer() => json!({ "type": "integer" }),
+        dt if dt.is_numeric() => json!({ "type": "number" }),
+        DataType::List(field)
+        | DataType::LargeList(field)
+        | DataType::ListView(field)
+        | DataType::LargeListView(field)
+        | DataType::FixedSizeList(field, _) => {
+            let items = json_schema_type(field.data_type());
+            let items = if field.is_nullable() {
+                json!({ "anyOf": [items, { "type": "null" }] })
+            } else {
+                items
+            };
+            json!({ "type": "array", "items": items })
+        }
+        DataType::Struct(fields) => json_schema_object(fields),
+        DataType::Map(field, _) => match field.data_type() {
+            DataType::Struct(entries) if entries.len() == 2 => json!({
+                "type": "object",
+                "additionalProperties": json_schema_type(entries[1].data_type()),
+            }),
+            _ => json!({ "type": "object" }),
+        },
+        DataType::Dictionary(_, value_type) => json_schema_type(value_type),
+        DataType::RunEndEncoded(_, values) => json_schema_type(values.data_type()),
+        // any value is allowed
+        DataType::Union(_, _) => json!({}),
+        // strings, binary and temporal values are all written as JSON strings
+        _ => json!({ "type": "string" }),
+    }
+}
+
+/// The layout of a [`PrintFormat::Table`], for callers such as TUIs that
+/// render and scroll the table themselves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    /// The header of each column
+    pub headers: Vec<String>,
+    /// The display width of each column
+    pub widths: Vec<usize>,
+    /// The formatted values of each row
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A writer coalescing the output of small batches into chunks of at least
+/// `target_bytes` before writing them to `inner`, to reduce the number of
+/// writes when streaming.
+///
+/// Buffered output older than `max_delay` is written with the next write or
+/// flush even if the chunk is smaller. The delay is only checked then, so it
+/// bounds the latency while output keeps arriving, not while the stream is
+/// idle. [`std::io::Write::flush`] only writes a full or overdue chunk, as the
+/// formats flush after every batch; call [`Self::finish`] at the end of the
+/// stream to write the rest, which dropping the writer also does.
+#[derive(Debug)]
+pub struct ChunkedWriter<W: std::io::Write> {
+    inner: W,
+    buffer: Vec<u8>,
+    target_bytes: usize,
+    max_delay: Option<Duration>,
+    /// When the oldest buffered output was written
+    buffered_since: Option<Instant>,
+}
+
+impl<W: std::io::Write> ChunkedWriter<W> {
+    /// A `target_bytes` of `0` writes every write through immediately
+    pub fn new(inner: W, target_bytes: usize, max_delay: Option<Duration>) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            target_bytes,
+            max_delay,
+            buffered_since: None,
+        }
+    }
+
+    /// The underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Write the rest of the buffered output and flush the underlying writer
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        self.write_buffer()?;
+        self.inner.flush()
+    }
+
+    /// Write the buffered output to the underlying writer. The output written
+    /// before a failure leaves the buffer, so a later write, [`Self::finish`]
+    /// or the drop only writes the rest.
+    fn write_buffer(&mut self) -> std::io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Ok(());
+            }
+            match self.inner.write(&self.buffer[written..]) {
+                Ok(0) => break Err(std::io::ErrorKind::WriteZero.into()),
+                Ok(len) => written += len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(err),
+            }
+        };
+        self.buffer.drain(..written);
+        if self.buffer.is_empty() {
+            self.buffered_since = None;
+        }
+        result
+    }
+
+    /// Whether the buffered output fills a chunk or is older than `max_delay`
+    fn chunk_ready(&self) -> bool {
+        let overdue = self.buffered_since.zip(self.max_delay).is_some_and(
+            |(buffered_since, max_delay)| buffered_since.elapsed() >= max_delay,
+        );
+        self.buffer.len() >= self.target_bytes || overdue
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.buffered_since.get_or_insert_with(Instant::now);
+        if self.chunk_ready() {
+            self.write_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.chunk_ready() {
+            self.write_buffer()?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: std::io::Write> Drop for ChunkedWriter<W> {
+    fn drop(&mut self) {
+        // errors can't be reported here, call `finish` to handle them
+        let _ = self.finish();
+    }
+}
+
+/// Default number of rows [`OutputStreamState`] buffers to compute the column
+/// widths before it starts printing rows
+pub const DEFAULT_PREVIEW_LIMIT: usize = 1000;
+
+/// How much output [`OutputStreamState`] buffers to compute the column widths
+/// before it starts printing rows
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PreviewPolicy {
+    /// Buffer this many rows
+    Rows(usize),
+    /// Buffer rows until their formatted values add up to this many bytes,
+    /// so a few wide rows end the preview sooner than many narrow ones
+    Bytes(usize),
+}
+
+impl Default for PreviewPolicy {
+    fn default() -> Self {
+        Self::Rows(DEFAULT_PREVIEW_LIMIT)
+    }
+}
+
+/// State for printing a stream of batches, by default in the
+/// [`PrintFormat::Table`] format.
+///
+/// The rows allowed by the [`PreviewPolicy`] are buffered to compute the
+/// column widths, then the header and the buffered rows are printed, and
+/// every following batch is printed as soon as it arrives using the same
+/// widths. Values wider than the previewed ones overflow their column.
+///
+/// Once `max_rows` rows are printed the remaining rows are dropped, and
+/// [`Self::finish`] marks the truncation with dotted lines before printing
+/// the bottom border.
+///
+/// The [`PrintFormat::Csv`], [`PrintFormat::Tsv`], [`PrintFormat::Json`] and
+/// [`PrintFormat::NdJson`] formats, see [`Self::with_format`], print the
+/// rows of every batch as soon as it arrives, while the other formats
+/// buffer the rows and print them in [`Self::finish`]. The CSV, TSV and
+/// NdJson rows are written by an arrow writer kept across the batches, so
+/// the CSV header only precedes the first batch.
+#[derive(Debug)]
+pub struct OutputStreamState<W: std::io::Write> {
+    /// The writer the output is printed to
+    writer: SharedWriter<W>,
+    /// The writer of the rows of the streamed batches, started by the first
+    /// one, see [`Self::start_batch_writer`]
+    batch_writer: Option<BatchWriter<W>>,
+    schema: SchemaRef,
+    /// The indices of the columns of the batches that are printed, with
+    /// [`PrintBatchesOptions::display_columns`]
+    projection: Option<Vec<usize>>,
+    format: PrintFormat,
+    /// How much to buffer before computing the column widths
+    preview_policy: PreviewPolicy,
+    /// The print options, including the maximum number of rows to print
+    options: PrintBatchesOptions,
+    /// Rows buffered until the column widths are known
+    preview_batches: Vec<RecordBatch>,
+    preview_row_count: usize,
+    /// The range of the values of the buffered rows, with
+    /// [`PrintBatchesOptions::header_range`]
+    preview_ranges: Option<ColumnRanges>,
+    /// The formatted size of the buffered rows, with [`PreviewPolicy::Bytes`]
+    preview_byte_count: usize,
+    /// The column widths, once computed
+    widths: Option<Vec<usize>>,
+    /// The alignment of the values of each column, from its type
+    alignments: Vec<Alignment>,
+    /// The alignment of the header of each column, from its type
+    header_alignments: Vec<Alignment>,
+    /// The decisions made for the schema of the printed batches
+    decisions: ColumnDecisions,
+    /// The number of rows printed or buffered so far
+    row_count: usize,
+    /// The number of rows dropped because of `max_rows`
+    omitted_rows: usize,
+    /// The number of non-empty batches printed in a streamed format other
+    /// than [`PrintFormat::Table`]
+    streamed_batches: usize,
+    /// The markers printed so far, with [`PrintBatchesOptions::print_legend`]
+    legend: LegendMarkers,
+    /// The last non-empty batch, with
+    /// [`PrintBatchesOptions::dedup_consecutive_batches`]
+    previous_batch: Option<RecordBatch>,
+    /// The header cells printed again with
+    /// [`PrintBatchesOptions::header_interval`], once the widths are known
+    repeated_header: Vec<String>,
+    /// The number of rows printed since the header
+    rows_since_header: usize,
+    /// Set once the writer failed with a broken pipe, after which nothing
+    /// more is written
+    closed: bool,
+}
+
+impl<W: std::io::Write> OutputStreamState<W> {
+    /// Print the batches of `schema` to `writer`
+    pub fn new(
+        writer: W,
+        schema: SchemaRef,
+        max_rows: MaxRows,
+        format_options: &FormatOptions,
+    ) -> Self {
+        Self {
+            writer: SharedWriter::new(writer),
+            batch_writer: None,
+            alignments: AlignmentPolicy::default().bodies(&schema),
+            header_alignments: AlignmentPolicy::default().headers(&schema),
+            decisions: ColumnDecisions::default(),
+            schema,
+            projection: None,
+            format: PrintFormat::Table,
+            preview_policy: PreviewPolicy::default(),
+            options: PrintBatchesOptions {
+                maxrows: max_rows,
+                format_options: format_options.clone(),
+                ..Default::default()
+            },
+            preview_batches: vec![],
+            preview_row_count: 0,
+            preview_ranges: None,
+            preview_byte_count: 0,
+            widths: None,
+            row_count: 0,
+            omitted_rows: 0,
+            streamed_batches: 0,
+            legend: LegendMarkers::default(),
+            previous_batch: None,
+            repeated_header: vec![],
+            rows_since_header: 0,
+            closed: false,
+        }
+    }
+
+    /// set the format to print the batches in
+    pub fn with_format(mut self, format: PrintFormat) -> Self {
+        self.format = format.for_output_path(self.options.output_path.as_deref());
+        self
+    }
+
+    /// set the number of rows to buffer before computing the column widths.
+    ///
+    /// A limit of `0` disables the preview for the lowest latency: the widths
+    /// are computed from the first non-empty batch, which is printed
+    /// immediately, and wider values in later batches overflow their column.
+    pub fn with_preview_limit(mut self, preview_limit: usize) -> Self {
+        self.preview_policy = PreviewPolicy::Rows(preview_limit);
+        self
+    }
+
+    /// set how much output to buffer before computing the column widths, see
+    /// [`Self::with_preview_limit`]
+    pub fn with_preview_policy(mut self, preview_policy: PreviewPolicy) -> Self {
+        self.preview_policy = preview_policy;
+        self
+    }
+
+    /// set the additional print options, whose row limit, header and format
+    /// options replace those passed to [`Self::new`]
+    pub fn with_options(mut self, options: PrintBatchesOptions) -> Self {
+        self.options = options;
+        // unknown display columns are left in the options, for the next
+        // batch to fail with
+        if let Some(columns) = &self.options.display_columns {
+            let projected =
+                display_projection(&self.schema, columns).and_then(|projection| {
+                    Ok((self.schema.project(&projection)?, projection))
+                });
+            if let Ok((schema, projection)) = projected {
+                self.schema = Arc::new(schema);
+                self.projection = Some(projection);
+                self.options.display_columns = None;
+            }
+        }
+        self.format = self
+            .format
+            .for_output_path(self.options.output_path.as_deref());
+        let schema = self.printed_schema();
+        self.alignments = self.options.alignment_policy.bodies(&schema);
+        self.header_alignments = self.options.alignment_policy.headers(&schema);
+        let overrides = &self.options.column_alignments;
+        override_alignments(&mut self.alignments, &schema, overrides);
+        override_alignments(&mut self.header_alignments, &schema, overrides);
+        self
+    }
+
+    /// The schema of the printed columns, which starts with the row number
+    /// column if [`PrintBatchesOptions::row_numbers`] is set
+    fn printed_schema(&self) -> SchemaRef {
+        if self.options.row_numbers {
+            row_number_schema(&self.schema)
+        } else {
+            Arc::clone(&self.schema)
+        }
+    }
+
+    /// Nothing is printed for a schema without columns or when no rows may be
+    /// shown
+    fn is_silent(&self) -> bool {
+        self.schema.fields().is_empty() || self.options.maxrows == MaxRows::Limited(0)
+    }
+
+    /// Print (or buffer) the rows of `batch` that fit within `max_rows`
+    pub fn process_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.check_display_columns()?;
+        if self.is_silent() || self.closed {
+            return Ok(());
+        }
+        let batch = &match &self.projection {
+            Some(projection) => batch.project(projection)?,
+            None => batch.clone(),
+        };
+        if self.options.dedup_consecutive_batches && batch.num_rows() > 0 {
+            // the row counts and schemas are compared before any values
+            let repeated = self.previous_batch.as_ref().is_some_and(|previous| {
+                previous.num_rows() == batch.num_rows() && previous == batch
+            });
+            if repeated {
+                self.options.warnings.warn(format!(
+                    "skipped a batch of {} rows identical to the previous batch",
+                    batch.num_rows()
+                ));
+                return Ok(());
+            }
+            self.previous_batch = Some(batch.clone());
+        }
+        let mut writer = self.writer.clone();
+        let result = self.write_batch(&mut writer, batch);
+        self.close_on_broken_pipe(result)
+    }
+
+    /// Fail if [`Self::with_options`] left display columns unresolved, as
+    /// the schema has no columns of some of their names
+    fn check_display_columns(&self) -> Result<()> {
+        match &self.options.display_columns {
+            Some(columns) => display_projection(&self.schema, columns).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark the output closed and return `Ok` if `result` failed with a
+    /// broken pipe that [`PrintBatchesOptions::ignore_broken_pipe`] ignores
+    fn close_on_broken_pipe(&mut self, result: Result<()>) -> Result<()> {
+        if result.is_err() && self.writer.broken() && self.options.ignore_broken_pipe {
+            self.closed = true;
+            return Ok(());
+        }
+        result
+    }
+
+    fn write_batch(
+        &mut self,
+        writer: &mut SharedWriter<W>,
+        batch: &RecordBatch,
+    ) -> Result<()> {
+        let batch = match self.options.maxrows {
+            MaxRows::Limited(max_rows)
+                if self.row_count + batch.num_rows() > max_rows =>
+            {
+                let kept = max_rows.saturating_sub(self.row_count);
+                self.omitted_rows += batch.num_rows() - kept;
+                batch.slice(0, kept)
+            }
+            _ => batch.clone(),
+        };
+        let first_row_number = self.row_count + 1;
+        self.row_count += batch.num_rows();
+        if self.format != PrintFormat::Table {
+            return self.write_records(writer, batch);
+        }
+        // the ranges are of the values, which preparing may format
+        if self.options.header_range && self.widths.is_none() {
+            if self.preview_ranges.is_none() {
+                self.preview_ranges = Some(ColumnRanges::try_new(&self.schema)?);
+            }
+            if let Some(ranges) = &mut self.preview_ranges {
+                ranges.update(&batch)?;
+            }
+        }
+        let mut batch = PrintFormat::Table.prepare_batch(&batch, &self.options)?;
+        if self.options.row_numbers {
+            batch = with_row_numbers(&batch, first_row_number)?;
+        }
+
+        match self.widths.clone() {
+            Some(widths) => self.print_rows(writer, &batch, &widths),
+            None => {
+                self.preview_row_count += batch.num_rows();
+                let previewed = match self.preview_policy {
+                    PreviewPolicy::Rows(limit) => self.preview_row_count >= limit,
+                    PreviewPolicy::Bytes(limit) => {
+                        self.preview_byte_count +=
+                            format_rows(&batch, &self.options.format_options)?
+                                .iter()
+                                .flatten()
+                                .map(|cell| cell.len())
+                                .sum::<usize>();
+                        self.preview_byte_count >= limit
+                    }
+                };
+                self.preview_batches.push(batch);
+                // fixed widths need no preview, otherwise wait for at least
+                // one row to size the columns
+                let preview_done = self.preview_row_count > 0 && previewed;
+                if preview_done || self.options.fixed_widths.is_some() {
+                    self.flush_preview(writer, true)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the rows are printed as each batch arrives, which the formats
+    /// other than [`PrintFormat::Table`] support unless an option formats the
+    /// output as a whole
+    fn streams_records(&self) -> bool {
+        let whole_output = self.options.section_label.is_some()
+            || self.options.base64_wrap
+            || self.options.encoding != OutputEncoding::Utf8
+            || self.options.batch_index_column
+            || self.options.pretty_csv
+            || self.options.trailing_delimiter
+            || self.options.describe
+            || self.options.width_histogram;
+        let streamed = matches!(
+            self.format,
+            PrintFormat::Csv
+                | PrintFormat::Tsv
+                | PrintFormat::Automatic
+                | PrintFormat::Json
+                | PrintFormat::NdJson
+        );
+        streamed && !whole_output
+    }
+
+    /// Print the rows of `batch` in a format other than [`PrintFormat::Table`]
+    /// if the format is streamed, or else buffer them until [`Self::finish`]
+    fn write_records(
+        &mut self,
+        writer: &mut SharedWriter<W>,
+        batch: RecordBatch,
+    ) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        if !self.streams_records() {
+            self.preview_batches.push(batch);
+            return Ok(());
+        }
+        let first = self.streamed_batches == 0;
+        self.streamed_batches += 1;
+        // broken pipes close this state rather than each printed batch, the
+        // batch is already limited to `maxrows`, and the header and the lines
+        // describing the output precede the first batch only
+        let mut options = PrintBatchesOptions {
+            ignore_broken_pipe: false,
+            maxrows: MaxRows::Unlimited,
+            with_header: first && self.options.with_header,
+            descriptor_line: first && self.options.descriptor_line,
+            source_sql: self.options.source_sql.clone().filter(|_| first),
+            csv_bom: first && self.options.csv_bom,
+            row_count_comment: false,
+            ..self.options.clone()
+        };
+        // the comment line of the preset also precedes the first batch only,
+        // see `start_batch_writer`
+        let preset = match self.format {
+            PrintFormat::Tsv => self.options.data_science_preset.clone(),
+            _ => None,
+        };
+        if preset.is_some() {
+            options = options.without_data_science_preset();
+        }
+        if first {
+            self.batch_writer = self.start_batch_writer(
+                writer,
+                &batch.schema(),
+                preset.as_ref(),
+                &mut options,
+            )?;
+        }
+        if let Some(batch_writer) = &mut self.batch_writer {
+            return batch_writer.write(&self.format.prepare_batch(&batch, &options)?);
+        }
+        match self.format {
+            PrintFormat::Json => {
+                // the records of each batch continue the array started by
+                // the first batch, which `write_end` closes
+                let batch = self.format.prepare_batch(&batch, &options)?;
+                let mut buffer: Vec<u8> = vec![];
+                self.format.print_json(&mut buffer, &[batch], &options)?;
+                let array = String::from_utf8_lossy(&buffer);
+                let array = array.trim_end();
+                let records = array
+                    .strip_prefix('[')
+                    .and_then(|array| array.strip_suffix(']'))
+                    .unwrap_or(array);
+                let separator = if first { "[" } else { "," };
+                write!(writer, "{separator}{records}")?;
+                Ok(())
+            }
+            PrintFormat::NdJson => {
+                // without trailing newlines the batches would run together
+                if !first && !options.ndjson_trailing_newline {
+                    writeln!(writer)?;
+                }
+                self.format
+                    .print_with_options(writer, batch.schema(), &[batch], &options)
+            }
+            _ => {
+                self.format
+                    .print_with_options(writer, batch.schema(), &[batch], &options)
+            }
+        }
+    }
+
+    /// Print the lines preceding the rows of the first streamed batch, and
+    /// start the arrow writer of the rows of every batch, unless the options
+    /// leave the format to [`PrintFormat::print_with_options`].
+    ///
+    /// The comment line of the TSV `preset` is printed here whatever prints
+    /// the rows, after the byte order mark, which `options` then leave out.
+    fn start_batch_writer(
+        &self,
+        writer: &mut SharedWriter<W>,
+        schema: &SchemaRef,
+        preset: Option<&DataSciencePreset>,
+        options: &mut PrintBatchesOptions,
+    ) -> Result<Option<BatchWriter<W>>> {
+        if let Some(preset) = preset {
+            if options.csv_bom {
+                writer.write_all(UTF8_BOM)?;
+                options.csv_bom = false;
+            }
+            print_column_types(writer, preset, schema)?;
+        }
+        let delimiter = match self.format {
+            PrintFormat::Csv | PrintFormat::Automatic => b',',
+            PrintFormat::Tsv => b'\t',
+            // escaping, replacing non-finite values and leaving out the final
+            // newline are left to `print_json`
+            PrintFormat::NdJson
+                if options.ndjson_trailing_newline
+                    && !options.json_ascii_escape
+                    && options.json_nan_policy == JsonNanPolicy::Null =>
+            {
+                let json_writer: LineDelimitedWriter<_> = JsonWriterBuilder::new()
+                    .with_explicit_nulls(options.empty_string_as_null)
+                    .build(writer.clone());
+                return Ok(Some(BatchWriter::NdJson(json_writer)));
+            }
+            _ => return Ok(None),
+        };
+        // fields quoted by hand and an invalid quote are left to
+        // `print_batches_with_sep`
+        if options.csv_quote_style == QuoteStyle::Always || !options.csv_quote.is_ascii()
+        {
+            return Ok(None);
+        }
+        if options.csv_bom {
+            writer.write_all(UTF8_BOM)?;
+        }
+        if options.descriptor_line {
+            self.format.print_descriptor_line(writer, schema)?;
+        }
+        if let Some(sql) = &options.source_sql {
+            self.format.print_source_sql(writer, sql)?;
+        }
+        let builder = csv_writer_builder(delimiter, options);
+        Ok(Some(BatchWriter::Csv(builder.build(writer.clone()))))
+    }
+
+    /// Compute the column widths from the buffered rows, then print the
+    /// header and the buffered rows. `more_rows` is set if rows may follow
+    /// the buffered ones.
+    fn flush_preview(
+        &mut self,
+        writer: &mut SharedWriter<W>,
+        more_rows: bool,
+    ) -> Result<()> {
+        let schema = self.printed_schema();
+        // the header is printed once, so it would understate the range of
+        // rows that follow the buffered ones
+        let mut ranges = match self.preview_ranges.take() {
+            Some(mut ranges) if !more_rows => ranges.labels()?,
+            _ => vec![],
+        };
+        if self.options.row_numbers && !ranges.is_empty() {
+            ranges.insert(0, None);
+        }
+        let mut widths = match &self.options.fixed_widths {
+            Some(widths) if widths.len() != self.schema.fields().len() => {
+                return exec_err!(
+                    "Expected {} fixed column widths, one per column, but got {}",
+                    self.schema.fields().len(),
+                    widths.len()
+                );
+            }
+            // the row number column is not one of the fixed width columns
+            Some(widths) if self.options.row_numbers => {
+                std::iter::once(self.row_count.max(1).to_string().len())
+                    .chain(widths.iter().copied())
+                    .collect()
+            }
+            Some(widths) => widths.clone(),
+            None => compute_column_widths(
+                &schema,
+                &self.preview_batches,
+                &ranges,
+                &self.options.format_options,
+                &self.options,
+            )?,
+        };
+        if self.options.row_numbers && more_rows {
+            // rows printed after the preview are numbered up to `max_rows`,
+            // or to a number of unknown width
+            let digits = match self.options.maxrows {
+                MaxRows::Limited(max_rows) => max_rows.to_string().len(),
+                MaxRows::Unlimited => UNLIMITED_ROW_NUMBER_DIGITS,
+            };
+            widths[0] = widths[0].max(digits);
+        }
+        if let Some(fit_width) = self.options.fit_width {
+            shrink_to_fit(&mut widths, fit_width);
+        }
+        print_header(
+            writer,
+            &schema,
+            &ranges,
+            &widths,
+            &self.header_alignments,
+            &self.options.format_options,
+            &self.options,
+        )?;
+        if self.options.header_interval.is_some() && self.options.table_header {
+            // the group row of the header is only printed once
+            let mut header = header_cells(
+                &schema,
+                &ranges,
+                &self.options.format_options,
+                &self.options,
+            )?;
+            if let Some(delimiter) = self.options.group_headers_delimiter {
+                header = column_groups(&header, delimiter).1;
+            }
+            self.repeated_header = wrap_header_cells(header, &widths, &self.options);
+        }
+        for batch in std::mem::take(&mut self.preview_batches) {
+            self.print_rows(writer, &batch, &widths)?;
+        }
+        self.widths = Some(widths);
+        Ok(())
+    }
+
+    /// Print the rows of `batch` padded to `widths`, printing the header
+    /// again every [`PrintBatchesOptions::header_interval`] rows
+    fn print_rows(
+        &mut self,
+        writer: &mut SharedWriter<W>,
+        batch: &RecordBatch,
+        widths: &[usize],
+    ) -> Result<()> {
+        if self.options.print_legend {
+            self.legend.observe(
+                batch,
+                widths,
+                &self.options.format_options,
+                &self.options,
+            )?;
+        }
+        let interval = match self.options.header_interval {
+            Some(interval) if interval > 0 && self.options.table_header => interval,
+            _ => {
+                return print_batch_with_widths(
+                    writer,
+                    batch,
+                    widths,
+                    &self.alignments,
+                    &mut self.decisions,
+                    &self.options.format_options,
+                    &self.options,
+                )
+            }
+        };
+        let style = self.options.border_style;
+        let header_styles = if self.options.colorize {
+            vec![Some(BOLD); self.repeated_header.len()]
+        } else {
+            vec![]
+        };
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            if self.rows_since_header == interval {
+                print_border(writer, widths, BorderLine::Separator, style)?;
+                print_row(
+                    writer,
+                    &self.repeated_header,
+                    widths,
+                    &self.header_alignments,
+                    None,
+                    &header_styles,
+                    &self.options,
+                )?;
+                print_border(writer, widths, BorderLine::Separator, style)?;
+                self.rows_since_header = 0;
+            }
+            let len = (interval - self.rows_since_header).min(batch.num_rows() - offset);
+            print_batch_with_widths(
+                writer,
+                &batch.slice(offset, len),
+                widths,
+                &self.alignments,
+                &mut self.decisions,
+                &self.options.format_options,
+                &self.options,
+            )?;
+            self.rows_since_header += len;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Print any buffered rows, the truncation marker if rows were dropped,
+    /// and the bottom border
+    pub fn finish(mut self) -> Result<()> {
+        self.check_display_columns()?;
+        if self.is_silent() || self.closed {
+            return Ok(());
+        }
+        let mut writer = self.writer.clone();
+        let result = self.write_end(&mut writer);
+        self.close_on_broken_pipe(result)
+    }
+
+    fn write_end(&mut self, writer: &mut SharedWriter<W>) -> Result<()> {
+        if self.format != PrintFormat::Table {
+            return self.write_records_end(writer);
+        }
+        if self.widths.is_none() {
+            self.flush_preview(writer, false)?;
+        }
+        let widths = self.widths.clone().unwrap_or_default();
+        if self.omitted_rows > 0 && self.options.collapsed_truncation {
+            print_omitted_rows_line(
+                writer,
+                &widths,
+                self.omitted_rows,
+                self.options.border_style,
+            )?;
+        } else if self.omitted_rows > 0 {
+            for _ in 0..self.options.truncation_rows.max(1) {
+                print_dotted_line(writer, &widths, self.options.border_style)?;
+            }
+        }
+        print_bottom_border(writer, &widths, self.options.border_style)?;
+        if self.options.row_count_footer {
+            print_row_count_footer(writer, self.row_count, self.omitted_rows)?;
+        }
+        if self.options.print_legend {
+            self.legend.print(writer, &self.options.format_options)?;
+        }
+        Ok(())
+    }
+
+    /// Close the JSON array of the streamed batches, or print the buffered
+    /// batches, or what the format prints for no rows if there were none
+    fn write_records_end(&mut self, writer: &mut SharedWriter<W>) -> Result<()> {
+        if self.streamed_batches > 0 {
+            if self.format == PrintFormat::Json {
+                writeln!(writer, "]")?;
+            }
+            let separated = matches!(
+                self.format,
+                PrintFormat::Csv | PrintFormat::Tsv | PrintFormat::Automatic
+            );
+            if separated && self.options.row_count_comment {
+                self.format
+                    .print_row_count_comment(writer, self.row_count)?;
+            }
+            return Ok(());
+        }
+        // the buffered batches are already limited to `maxrows`
+        self.format.print_with_options(
+            writer,
+            Arc::clone(&self.schema),
+            &self.preview_batches,
+            &PrintBatchesOptions {
+                ignore_broken_pipe: false,
+                maxrows: MaxRows::Unlimited,
+                ..self.options.clone()
+            },
+        )
+    }
+}
+
+/// The markers of [`PrintBatchesOptions::print_legend`] that were printed
+#[derive(Debug, Default)]
+struct LegendMarkers {
+    /// A null was printed as the `null` string of the format options
+    null: bool,
+    /// A value was cut short and ended with `…`
+    truncated: bool,
+}
+
+impl LegendMarkers {
+    /// Record the markers used to print the rows of `batch` padded to `widths`
+    fn observe(
+        &mut self,
+        batch: &RecordBatch,
+        widths: &[usize],
+        format_options: &FormatOptions,
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        // a `Null` array has no null buffer, but every value is null
+        self.null |= batch.num_rows() > 0
+            && batch.columns().iter().any(|column| {
+                column.null_count() > 0 || column.data_type() == &DataType::Null
+            });
+        // values are only ellipsized when not truncated to fixed widths, see
+        // `print_row`
+        let ellipsized = (options.max_column_width.is_some()
+            || options.fit_width.is_some())
+            && options.fixed_widths.is_none()
+            && options.width_stable_after.is_none();
+        if ellipsized && !self.truncated {
+            self.truncated = format_rows(batch, format_options)?.iter().any(|row| {
+                row.iter().zip(widths).any(|(cell, width)| {
+                    cell_lines(cell).any(|line| line_width(line) > *width)
+                })
+            });
+        }
+        Ok(())
+    }
+
+    /// Print a line naming the markers used, if any
+    fn print<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        format_options: &FormatOptions,
+    ) -> Result<()> {
+        let mut entries = vec![];
+        // an empty null string needs no explaining
+        if self.null && !format_options.null.is_empty() {
+            entries.push(format!("{} = null", format_options.null));
+        }
+        if self.truncated {
+            entries.push("… = truncated".to_string());
+        }
+        if !entries.is_empty() {
+            writeln!(writer, "{}", entries.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Narrow the widest of `widths` to a common width, the widest at which a
+/// table with these column widths is at most `max_width` wide, or to `1` if
+/// none is. The width left over is spread over the narrowed columns from the
+/// left, as if the widest column were narrowed one at a time, rightmost first
+/// among equal widths
+fn shrink_to_fit(widths: &mut [usize], max_width: usize) {
+    // each column adds `| ` and ` ` around its cells, and the row ends with `|`
+    let budget = max_width.saturating_sub(widths.len() * 3 + 1);
+    let mut sorted = widths.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let mut rest = sorted.iter().sum::<usize>();
+    if rest <= budget {
+        return;
+    }
+    // with the `capped` widest columns narrowed to `cap`, the others are
+    // left as they are
+    let mut cap = 1;
+    for (capped, width) in sorted.iter().enumerate() {
+        rest -= width;
+        let fits = budget.saturating_sub(rest) / (capped + 1);
+        if fits >= sorted.get(capped + 1).copied().unwrap_or_default() {
+            cap = fits.max(1);
+            break;
+        }
+    }
+    let narrowed = widths.iter().map(|width| (*width).min(cap)).sum::<usize>();
+    let mut left_over = budget.saturating_sub(narrowed);
+    for width in widths.iter_mut().filter(|width| **width > cap) {
+        *width = cap + usize::from(left_over > 0);
+        left_over = left_over.saturating_sub(1);
+    }
+}
+
+/// The digits of the row numbers [`PrintFormat::Table`] streamed without a
+/// [`MaxRows::Limited`] reserves room for, as the number of rows is unknown
+/// when the column widths are fixed
+const UNLIMITED_ROW_NUMBER_DIGITS: usize = 6;
+
+/// Print a `(N rows)` line, or `(showing N of M rows)` if `omitted_rows` were
+/// not printed
+fn print_row_count_footer<W: std::io::Write>(
+    writer: &mut W,
+    printed_rows: usize,
+    omitted_rows: usize,
+) -> Result<()> {
+    let total_rows = printed_rows + omitted_rows;
+    let noun = if total_rows == 1 { "row" } else { "rows" };
+    if omitted_rows > 0 {
+        writeln!(writer, "(showing {printed_rows} of {total_rows} {noun})")?;
+    } else {
+        writeln!(writer, "({total_rows} {noun})")?;
+    }
+    Ok(())
+}
+
+/// A writer recording whether writing to `inner` failed with a broken pipe,
+/// as writers such as the CSV writer convert the [`std::io::Error`] into
+/// errors that no longer tell
+#[derive(Debug)]
+struct PipeWriter<W> {
+    inner: W,
+    broken: bool,
+}
+
+impl<W: std::io::Write> PipeWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            broken: false,
+        }
+    }
+
+    /// `Ok` if `result` failed because of a broken pipe
+    fn ignore_broken(&self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(_) if self.broken => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for PipeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf).inspect_err(|err| {
+            self.broken |= err.kind() == std::io::ErrorKind::BrokenPipe;
+        })
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush().inspect_err(|err| {
+            self.broken |= err.kind() == std::io::ErrorKind::BrokenPipe;
+        })
+    }
+}
+
+/// The writer of an [`OutputStreamState`], shared with the [`BatchWriter`]
+/// of the streamed batches so that the state still prints the lines around
+/// their rows
+#[derive(Debug)]
+struct SharedWriter<W>(Arc<Mutex<PipeWriter<W>>>);
+
+impl<W: std::io::Write> SharedWriter<W> {
+    fn new(inner: W) -> Self {
+        Self(Arc::new(Mutex::new(PipeWriter::new(inner))))
+    }
+
+    /// Whether writing failed with a broken pipe
+    fn broken(&self) -> bool {
+        self.0.lock().broken
+    }
+}
+
+impl<W> Clone for SharedWriter<W> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for SharedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().flush()
+    }
+}
+
+/// The arrow writer an [`OutputStreamState`] keeps across the streamed
+/// batches to write their rows, so the CSV header is only written once
+#[derive(Debug)]
+enum BatchWriter<W: std::io::Write> {
+    Csv(CsvWriter<SharedWriter<W>>),
+    NdJson(LineDelimitedWriter<SharedWriter<W>>),
+}
+
+impl<W: std::io::Write> BatchWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match self {
+            Self::Csv(writer) => writer.write(batch)?,
+            Self::NdJson(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+}
+
+/// The running `[min..max]` range of the values of each numeric column, for
+/// [`PrintBatchesOptions::header_range`]
+#[derive(Debug)]
+struct ColumnRanges {
+    /// The minimum and maximum accumulators of each numeric column
+    accumulators: Vec<Option<(MinAccumulator, MaxAccumulator)>>,
+}
+
+impl ColumnRanges {
+    fn try_new(schema: &SchemaRef) -> Result<Self> {
+        let accumulators = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                if !field.data_type().is_numeric() {
+                    return Ok(None);
+                }
+                Ok(Some((
+                    MinAccumulator::try_new(field.data_type())?,
+                    MaxAccumulator::try_new(field.data_type())?,
+                )))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { accumulators })
+    }
+
+    /// Include the values of `batch`, which must have the schema the ranges
+    /// were created for, and not be formatted by
+    /// [`PrintFormat::prepare_batch`] yet
+    fn update(&mut self, batch: &RecordBatch) -> Result<()> {
+        for (accumulators, column) in self.accumulators.iter_mut().zip(batch.columns()) {
+            if let Some((min, max)) = accumulators {
+                let values = [Arc::clone(column)];
+                min.update_batch(&values)?;
+                max.update_batch(&values)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the `[min..max]` label of each column, or `None` for other
+    /// columns and columns with only nulls
+    fn labels(&mut self) -> Result<Vec<Option<String>>> {
+        self.accumulators
+            .iter_mut()
+            .map(|accumulators| {
+                let Some((min, max)) = accumulators else {
+                    return Ok(None);
+                };
+                let (min, max) = (min.evaluate()?, max.evaluate()?);
+                Ok((!min.is_null()).then(|| format!("[{min}..{max}]")))
+            })
+            .collect()
+    }
+}
+
+/// Return the `[min..max]` label of each column of `batches` with
+/// [`PrintBatchesOptions::header_range`], or no labels without it. The
+/// batches must not be formatted by [`PrintFormat::prepare_batch`] yet.
+fn column_ranges(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    options: &PrintBatchesOptions,
+) -> Result<Vec<Option<String>>> {
+    if !options.header_range {
+        return Ok(vec![]);
+    }
+    let mut ranges = ColumnRanges::try_new(schema)?;
+    for batch in batches {
+        ranges.update(batch)?;
+    }
+    ranges.labels()
+}
+
+/// Return the header cell of each column, which is the field name or its
+/// [`PrintBatchesOptions::header_from_metadata`] label, and includes the data
+/// type when `types_info` is set, and the range label of the column in
+/// `ranges`, if any, see [`column_ranges`]
+fn header_cells(
+    schema: &SchemaRef,
+    ranges: &[Option<String>],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<Vec<String>> {
+    Ok(schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let mut name = options
+                .header_from_metadata
+                .as_ref()
+                .and_then(|key| field.metadata().get(key))
+                .unwrap_or(field.name())
+                .to_string();
+            if let Some(range) = ranges.get(idx).and_then(Option::as_ref) {
+                name.push(' ');
+                name.push_str(range);
+            }
+            if let Some((_, ascending)) = options
+                .sort_indicators
+                .iter()
+                .find(|(column, _)| column == field.name())
+            {
+                name.push_str(if *ascending { " ▲" } else { " ▼" });
+            }
+            if format_options.types_info {
+                format!("{name}\n{}", field.data_type())
+            } else {
+                name
+            }
+        })
+        .collect())
+}
+
+/// Return the formatted values of `batch`, one `Vec` per row
+fn format_rows(
+    batch: &RecordBatch,
+    format_options: &FormatOptions,
+) -> Result<Vec<Vec<String>>> {
+    let display_options: arrow::util::display::FormatOptions =
+        format_options.try_into()?;
+    let null_typed = null_typed_columns(&batch.schema());
+    format_rows_with(batch, &display_options, &null_typed, &format_options.null)
+}
+
+/// Return the formatted values of `batch`, one `Vec` per row, where the
+/// display options and the [`null_typed_columns`] of its schema were
+/// decided beforehand, such as once for all the batches of a stream
+fn format_rows_with(
+    batch: &RecordBatch,
+    display_options: &arrow::util::display::FormatOptions,
+    null_typed: &[bool],
+    null: &str,
+) -> Result<Vec<Vec<String>>> {
+    // every value of a `Null` column is null, so it needs no formatter
+    let formatters = batch
+        .columns()
+        .iter()
+        .zip(null_typed)
+        .map(|(column, null_typed)| {
+            if *null_typed {
+                Ok(None)
+            } else {
+                ArrayFormatter::try_new(column.as_ref(), display_options).map(Some)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let cells = formatters
+            .iter()
+            .map(|formatter| match formatter {
+                Some(formatter) => formatter.value(row).try_to_string(),
+                None => Ok(null.to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.push(cells);
+    }
+    Ok(rows)
+}
+
+/// Whether each column of `schema` is of the `Null` type, whose arrays have
+/// no null buffer although every value is null
+fn null_typed_columns(schema: &SchemaRef) -> Vec<bool> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| field.data_type() == &DataType::Null)
+        .collect()
+}
+
+/// The decisions depending only on the columns of the batches, rather than
+/// on their values, made once per schema instead of for every batch.
+///
+/// The batches of a stream share their [`SchemaRef`], so the decisions are
+/// kept while the schema is the same pointer, and made again when a batch
+/// brings another schema, such as when the schema evolves within the input.
+#[derive(Debug, Default)]
+struct ColumnDecisions {
+    /// The schema the decisions were made for
+    schema: Option<SchemaRef>,
+    /// See [`null_typed_columns`]
+    null_typed: Vec<bool>,
+    /// Whether each column is numeric, for [`PrintBatchesOptions::colorize`]
+    numeric: Vec<bool>,
+    /// Whether each column is one of the
+    /// [`PrintBatchesOptions::categorical_color_columns`]
+    categorical: Vec<bool>,
+}
+
+impl ColumnDecisions {
+    /// Make the decisions for `schema`, unless they were made for it already
+    fn update(&mut self, schema: &SchemaRef, options: &PrintBatchesOptions) {
+        if self
+            .schema
+            .as_ref()
+            .is_some_and(|decided| Arc::ptr_eq(decided, schema))
+        {
+            return;
+        }
+        let fields = schema.fields();
+        self.null_typed = null_typed_columns(schema);
+        self.numeric = fields
+            .iter()
+            .map(|field| field.data_type().is_numeric())
+            .collect();
+        self.categorical = fields
+            .iter()
+            .map(|field| options.categorical_color_columns.contains(field.name()))
+            .collect();
+        self.schema = Some(Arc::clone(schema));
+    }
+}
+
+/// The byte index and display width of each character of `line`, where the
+/// characters of ANSI CSI sequences such as `\x1b[31m`, which terminals do
+/// not show, have no width
+fn char_widths(line: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    enum State {
+        Text,
+        Escape,
+        Csi,
+    }
+    let mut state = State::Text;
+    line.char_indices().map(move |(idx, c)| {
+        let width = match state {
+            State::Text if c == '\x1b' => {
+                state = State::Escape;
+                0
+            }
+            State::Escape if c == '[' => {
+                state = State::Csi;
+                0
+            }
+            State::Csi => {
+                // a CSI sequence ends with a byte in `@..=~`
+                if ('@'..='~').contains(&c) {
+                    state = State::Text;
+                }
+                0
+            }
+            _ => {
+                state = State::Text;
+                c.width().unwrap_or(0)
+            }
+        };
+        (idx, width)
+    })
+}
+
+/// The display width of a single line of a cell, ignoring ANSI escapes
+fn line_width(line: &str) -> usize {
+    char_widths(line).map(|(_, width)| width).sum()
+}
+
+/// The display width of a cell, which is the width of its widest line
+fn cell_width(cell: &str) -> usize {
+    cell_lines(cell).map(line_width).max().unwrap_or(0)
+}
+
+/// The lines of a cell, broken at `\n`, `\r\n` and a lone `\r`, as a
+/// carriage return printed within a line would move the cursor back over the
+/// border
+fn cell_lines(cell: &str) -> impl Iterator<Item = &str> {
+    cell.split('\n')
+        .flat_map(|line| line.strip_suffix('\r').unwrap_or(line).split('\r'))
+}
+
+/// Pad a single line of a cell with spaces to `width`
+fn pad_cell(line: &str, width: usize) -> String {
+    format!(
+        "{line}{}",
+        " ".repeat(width.saturating_sub(line_width(line)))
+    )
+}
+
+/// How the cells of a [`PrintFormat::Table`] column are padded to its width
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Alignment {
+    Left,
+    Right,
+    /// Padded equally on both sides, with the extra space of an odd padding
+    /// on the right
+    Center,
+}
+
+impl Alignment {
+    /// Pad a single line of a cell with spaces to `width`
+    fn pad(&self, line: &str, width: usize) -> String {
+        let padding = width.saturating_sub(line_width(line));
+        match self {
+            Self::Left => pad_cell(line, width),
+            Self::Right => format!("{}{line}", " ".repeat(padding)),
+            Self::Center => format!(
+                "{}{line}{}",
+                " ".repeat(padding / 2),
+                " ".repeat(padding - padding / 2)
+            ),
+        }
+    }
+}
+
+/// The alignment of the [`PrintFormat::Table`] headers and values of the
+/// numeric columns and of the other columns
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AlignmentPolicy {
+    pub numeric_header: Alignment,
+    pub numeric_body: Alignment,
+    pub other_header: Alignment,
+    pub other_body: Alignment,
+}
+
+impl Default for AlignmentPolicy {
+    /// Numbers are right aligned so their digits line up, everything else is
+    /// left aligned
+    fn default() -> Self {
+        Self {
+            numeric_header: Alignment::Right,
+            numeric_body: Alignment::Right,
+            other_header: Alignment::Left,
+            other_body: Alignment::Left,
+        }
+    }
+}
+
+/// Replace the alignment of each column of `schema` whose name, or else whose
+/// index, is in `overrides`
+fn override_alignments(
+    alignments: &mut [Alignment],
+    schema: &SchemaRef,
+    overrides: &HashMap<String, Alignment>,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+    for (idx, (field, alignment)) in schema.fields().iter().zip(alignments).enumerate() {
+        let forced = overrides
+            .get(field.name())
+            .or_else(|| overrides.get(&idx.to_string()));
+        if let Some(forced) = forced {
+            *alignment = *forced;
+        }
+    }
+}
+
+impl AlignmentPolicy {
+    /// The alignment of the header of each column of `schema`
+    fn headers(&self, schema: &SchemaRef) -> Vec<Alignment> {
+        self.of_columns(schema, self.numeric_header, self.other_header)
+    }
+
+    /// The alignment of the values of each column of `schema`
+    fn bodies(&self, schema: &SchemaRef) -> Vec<Alignment> {
+        self.of_columns(schema, self.numeric_body, self.other_body)
+    }
+
+    fn of_columns(
+        &self,
+        schema: &SchemaRef,
+        numeric: Alignment,
+        other: Alignment,
+    ) -> Vec<Alignment> {
+        schema
+            .fields()
+            .iter()
+            .map(|field| {
+                if field.data_type().is_numeric() {
+                    numeric
+                } else {
+                    other
+                }
+            })
+            .collect()
+    }
+}
+
+/// Cut a single line of a cell wider than `width` display columns short,
+/// ending it with `…`
+fn ellipsize_cell(line: &str, width: usize) -> Cow<'_, str> {
+    if line_width(line) <= width {
+        return Cow::Borrowed(line);
+    }
+    Cow::Owned(format!("{}…", truncate_cell(line, width.saturating_sub(1))))
+}
+
+/// Truncate a single line of a cell to at most `width` display columns
+fn truncate_cell(line: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (idx, char_width) in char_widths(line) {
+        used += char_width;
+        if used > width {
+            return &line[..idx];
+        }
+    }
+    line
+}
+
+/// Split `line` into words that each end with a `_` or space, where a long
+/// name can be wrapped
+fn header_words(line: &str) -> impl Iterator<Item = &str> {
+    line.split_inclusive(['_', ' '])
+}
+
+/// Wrap a single line of a header cell onto lines at most `width` wide,
+/// breaking within a word only if the word itself is wider
+fn wrap_header_line(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || line.width() <= width {
+        return vec![line.to_string()];
+    }
+
+    let mut lines = vec![];
+    let mut current = String::new();
+    for word in header_words(line) {
+        if current.width() + word.width() <= width {
+            current.push_str(word);
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current).trim_end().to_string());
+        }
+        let mut rest = word;
+        while rest.width() > width {
+            let chunk = truncate_cell(rest, width);
+            // always make progress, even if a character is wider than `width`
+            let chunk = if chunk.is_empty() {
+                &rest[..rest.chars().next().map_or(0, char::len_utf8)]
+            } else {
+                chunk
+            };
+            lines.push(chunk.to_string());
+            rest = &rest[chunk.len()..];
+        }
+        current.push_str(rest);
+    }
+    if !current.is_empty() {
+        lines.push(current.trim_end().to_string());
+    }
+    lines
+}
+
+/// Wrap the header `cells` to `widths` if `wrap_headers` is set
+fn wrap_header_cells(
+    cells: Vec<String>,
+    widths: &[usize],
+    options: &PrintBatchesOptions,
+) -> Vec<String> {
+    if !options.wrap_headers {
+        return cells;
+    }
+    cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| {
+            cell.split('\n')
+                .flat_map(|line| wrap_header_line(line, *width))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Compute the width of each column: the widest of its header (when printed)
+/// with its label in `ranges`, and values, but at least `min_column_width`,
+/// see [`PrintBatchesOptions::width_stable_after`]
+fn compute_column_widths(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    ranges: &[Option<String>],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<Vec<usize>> {
+    if let Some(max_width) = options.max_column_width {
+        let options = PrintBatchesOptions {
+            max_column_width: None,
+            ..options.clone()
+        };
+        let widths =
+            compute_column_widths(schema, batches, ranges, format_options, &options)?;
+        return Ok(widths
+            .into_iter()
+            .map(|width| width.min(max_width.max(1)))
+            .collect());
+    }
+
+    let mut widths: Vec<usize> = if options.table_header {
+        let mut header = header_cells(schema, ranges, format_options, options)?;
+        let mut groups = vec![];
+        if let Some(delimiter) = options.group_headers_delimiter {
+            (groups, header) = column_groups(&header, delimiter);
+        }
+        let mut widths: Vec<usize> = header
+            .iter()
+            .map(|cell| {
+                let width = if options.wrap_headers {
+                    // the header wraps, so only its widest word must fit
+                    cell.split('\n')
+                        .flat_map(header_words)
+                        .map(|word| word.trim_end().width())
+                        .max()
+                        .unwrap_or(0)
+                } else {
+                    cell_width(cell)
+                };
+                width.max(options.min_column_width)
+            })
+            .collect();
+        // widen the last column of a group whose name is wider than it spans
+        let mut end = 0;
+        for (group, len) in groups {
+            end += len;
+            let spanned = span_width(&widths[end - len..end]);
+            widths[end - 1] += cell_width(&group).saturating_sub(spanned);
+        }
+        widths
+    } else {
+        vec![options.min_column_width; schema.fields().len()]
+    };
+
+    let display_options: arrow::util::display::FormatOptions =
+        format_options.try_into()?;
+    let mut decisions = ColumnDecisions::default();
+    let mut stable_rows = 0;
+    for batch in batches {
+        decisions.update(&batch.schema(), options);
+        // format the rows in chunks, so the rest of a large batch is not
+        // formatted once the widths are stable
+        let chunk_size = options
+            .width_stable_after
+            .unwrap_or(batch.num_rows())
+            .max(1);
+        for offset in (0..batch.num_rows()).step_by(chunk_size) {
+            let chunk = batch.slice(offset, chunk_size.min(batch.num_rows() - offset));
+            let rows = format_rows_with(
+                &chunk,
+                &display_options,
+                &decisions.null_typed,
+                &format_options.null,
+            )?;
+            for row in rows {
+                let mut grew = false;
+                for (width, cell) in widths.iter_mut().zip(&row) {
+                    let cell_width = cell_width(cell);
+                    if cell_width > *width {
+                        *width = cell_width;
+                        grew = true;
+                    }
+                }
+                stable_rows = if grew { 0 } else { stable_rows + 1 };
+                if options
+                    .width_stable_after
+                    .is_some_and(|stable_after| stable_rows >= stable_after)
+                {
+                    return Ok(widths);
+                }
+            }
+        }
+    }
+    Ok(widths)
+}
+
+/// Print a row of cells padded to `widths`, where columns without an entry
+/// in `alignments` are left aligned. Cells containing newlines span multiple
+/// lines.
+fn print_row<W: std::io::Write>(
+    writer: &mut W,
+    cells: &[String],
+    widths: &[usize],
+    alignments: &[Alignment],
+    style: Option<&str>,
+    cell_styles: &[Option<&str>],
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let cell_lines: Vec<Vec<&str>> = cells
+        .iter()
+        .map(|cell| cell_lines(cell).collect())
+        .collect();
+    let height = cell_lines.iter().map(Vec::len).max().unwrap_or(0);
+
+    let vertical = options.border_style.vertical();
+    for i in 0..height {
+        let mut line = String::from(vertical);
+        for (idx, (lines, width)) in cell_lines.iter().zip(widths).enumerate() {
+            let value = lines.get(i).copied().unwrap_or_default();
+            let value = if options.fixed_widths.is_some()
+                || options.width_stable_after.is_some()
+            {
+                Cow::Borrowed(truncate_cell(value, *width))
+            } else if options.max_column_width.is_some() || options.fit_width.is_some() {
+                ellipsize_cell(value, *width)
+            } else {
+                Cow::Borrowed(value)
+            };
+            let value = match cell_styles.get(idx).copied().flatten() {
+                // the reset also ends the row style, so it is started again
+                Some(cell_style) if !value.is_empty() => Cow::Owned(format!(
+                    "\x1b[{cell_style}m{value}\x1b[0m{}",
+                    style
+                        .map(|style| format!("\x1b[{style}m"))
+                        .unwrap_or_default()
+                )),
+                _ => value,
+            };
+            let alignment = alignments.get(idx).unwrap_or(&Alignment::Left);
+            line.push(' ');
+            line.push_str(&alignment.pad(&value, *width));
+            line.push(' ');
+            line.push(vertical);
+        }
+        // the style wraps the padded line, so it does not affect the widths
+        match style {
+            Some(style) => writeln!(writer, "\x1b[{style}m{line}\x1b[0m")?,
+            None => writeln!(writer, "{line}")?,
+        }
+    }
+    Ok(())
+}
+
+/// Print a [`PrintFormat::FixedWidth`] row, where a cell spanning several
+/// lines spans several lines of output
+fn print_fixed_width_row<W: std::io::Write>(
+    writer: &mut W,
+    cells: &[String],
+    widths: &[usize],
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let cell_lines: Vec<Vec<&str>> = cells
+        .iter()
+        .map(|cell| cell_lines(cell).collect())
+        .collect();
+    let height = cell_lines.iter().map(Vec::len).max().unwrap_or(0);
+    let gap = " ".repeat(options.column_gap);
+
+    for i in 0..height {
+        let fields: Vec<String> = cell_lines
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(idx, (lines, width))| {
+                let value = lines.get(i).copied().unwrap_or_default();
+                let value = match options.max_column_width {
+                    Some(_) => ellipsize_cell(value, *width),
+                    None => Cow::Borrowed(value),
+                };
+                if idx + 1 == widths.len() && !options.pad_right {
+                    value.into_owned()
+                } else {
+                    pad_cell(&value, *width)
+                }
+            })
+            .collect();
+        writeln!(writer, "{}", fields.join(&gap))?;
+    }
+    Ok(())
+}
+
+/// Print the batches in the [`PrintFormat::FixedWidth`] format
+fn print_fixed_width<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    with_header: bool,
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let options = PrintBatchesOptions {
+        table_header: with_header,
+        ..options.clone()
+    };
+    let widths = compute_column_widths(schema, batches, &[], format_options, &options)?;
+    if with_header {
+        let header = header_cells(schema, &[], format_options, &options)?;
+        let header = wrap_header_cells(header, &widths, &options);
+        print_fixed_width_row(writer, &header, &widths, &options)?;
+    }
+    for batch in batches {
+        for row in format_rows(batch, format_options)? {
+            print_fixed_width_row(writer, &row, &widths, &options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Escape a Markdown table cell, so `|` and line breaks do not end the cell
+fn escape_markdown(cell: &str) -> String {
+    cell.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// Print the batches in the [`PrintFormat::Markdown`] format, where string
+/// values have been escaped by [`PrintFormat::prepare_batch`]
+fn print_markdown<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    // a Markdown table always has a header, and `|` between its cells
+    let options = PrintBatchesOptions {
+        table_header: true,
+        border_style: BorderStyle::Ascii,
+        ..options.clone()
+    };
+    let header: Vec<String> = header_cells(schema, &[], format_options, &options)?
+        .iter()
+        .map(|cell| escape_markdown(cell))
+        .collect();
+    let mut widths =
+        compute_column_widths(schema, batches, &[], format_options, &options)?;
+    for (width, cell) in widths.iter_mut().zip(&header) {
+        *width = (*width).max(cell_width(cell));
+    }
+
+    print_row(writer, &header, &widths, &[], None, &[], &options)?;
+    let mut separator = String::from("|");
+    for width in &widths {
+        separator.push_str(&"-".repeat(width + 2));
+        separator.push('|');
+    }
+    writeln!(writer, "{separator}")?;
+    for batch in batches {
+        for row in format_rows(batch, format_options)? {
+            print_row(writer, &row, &widths, &[], None, &[], &options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Escape the characters of `cell` that are special in HTML text and
+/// attribute values
+fn escape_html(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Print a `<tr>` row of `tag` cells, such as `<td>`, where cells with an
+/// entry in `classes` have that `class` attribute
+fn print_html_row<W: std::io::Write>(
+    writer: &mut W,
+    cells: &[String],
+    tag: &str,
+    classes: &[Option<&str>],
+) -> Result<()> {
+    let mut line = String::from("<tr>");
+    for (idx, cell) in cells.iter().enumerate() {
+        let cell = escape_html(cell);
+        match classes.get(idx).copied().flatten() {
+            Some(class) => {
+                line.push_str(&format!("<{tag} class=\"{class}\">{cell}</{tag}>"))
+            }
+            None => line.push_str(&format!("<{tag}>{cell}</{tag}>")),
+        }
+    }
+    line.push_str("</tr>");
+    writeln!(writer, "{line}")?;
+    Ok(())
+}
+
+/// The class of the HTML cell of the value at `row` of `column`, see
+/// [`PrintBatchesOptions::html_css_classes`]
+fn html_css_class(column: &dyn Array, row: usize) -> Option<&'static str> {
+    let data_type = column.data_type();
+    // a `Null` array has no null buffer, but every value is null
+    if column.is_null(row) || data_type == &DataType::Null {
+        Some("null")
+    } else if data_type.is_numeric() {
+        Some("num")
+    } else if matches!(
+        data_type,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+    ) {
+        Some("str")
+    } else {
+        None
+    }
+}
+
+/// Print the batches in the [`PrintFormat::Html`] format
+fn print_html<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    match &options.html_table_class {
+        Some(class) => writeln!(writer, "<table class=\"{}\">", escape_html(class))?,
+        None => writeln!(writer, "<table>")?,
+    }
+    // an HTML table always has a header
+    let options = PrintBatchesOptions {
+        table_header: true,
+        ..options.clone()
+    };
+    writeln!(writer, "<thead>")?;
+    let header = header_cells(schema, &[], format_options, &options)?;
+    print_html_row(writer, &header, "th", &[])?;
+    writeln!(writer, "</thead>")?;
+    writeln!(writer, "<tbody>")?;
+    for batch in batches {
+        for (idx, row) in format_rows(batch, format_options)?.iter().enumerate() {
+            let classes: Vec<Option<&str>> = if options.html_css_classes {
+                batch
+                    .columns()
+                    .iter()
+                    .map(|column| html_css_class(column.as_ref(), idx))
+                    .collect()
+            } else {
+                vec![]
+            };
+            print_html_row(writer, row, "td", &classes)?;
+        }
+    }
+    writeln!(writer, "</tbody>")?;
+    writeln!(writer, "</table>")?;
+    Ok(())
+}
+
+/// A logfmt key for a field name, where the characters that would end the
+/// key are replaced with `_`
+fn logfmt_key(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c == '=' || c == '"' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// A logfmt value, quoted if it contains a space, `=` or `"`
+fn logfmt_value(value: &str) -> String {
+    if !value.contains(|c: char| c == '=' || c == '"' || c.is_whitespace()) {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Print the batches in the [`PrintFormat::Logfmt`] format
+fn print_logfmt<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let keys: Vec<String> = schema
+        .fields()
+        .iter()
+        .map(|field| logfmt_key(field.name()))
+        .collect();
+    let level = options
+        .logfmt_level
+        .as_deref()
+        .map(|level| format!("level={} ", logfmt_value(level)));
+    for batch in batches {
+        for row in format_rows(batch, format_options)? {
+            let pairs: Vec<String> = keys
+                .iter()
+                .zip(&row)
+                .map(|(key, value)| format!("{key}={}", logfmt_value(value)))
+                .collect();
+            writeln!(
+                writer,
+                "{}{}",
+                level.as_deref().unwrap_or_default(),
+                pairs.join(" ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Escape the characters of `cell` that are special in LaTeX text
+fn escape_latex(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '_' | '%' | '&' | '#' | '$' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The `tabular` column spec of `schema`, with numeric columns right aligned
+fn latex_column_spec(schema: &SchemaRef) -> String {
+    schema
+        .fields()
+        .iter()
+        .map(|field| {
+            if field.data_type().is_numeric() {
+                'r'
+            } else {
+                'l'
+            }
+        })
+        .collect()
+}
+
+/// Print a `tabular` row, ended by `\\`
+fn print_latex_row<W: std::io::Write>(writer: &mut W, cells: &[String]) -> Result<()> {
+    let cells: Vec<String> = cells.iter().map(|cell| escape_latex(cell)).collect();
+    writeln!(writer, "{} \\\\", cells.join(" & "))?;
+    Ok(())
+}
+
+/// Print the batches in the [`PrintFormat::Latex`] format
+fn print_latex<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    // a LaTeX table always has a header
+    let options = PrintBatchesOptions {
+        table_header: true,
+        ..options.clone()
+    };
+    writeln!(
+        writer,
+        "\\begin{{tabular}}{{{}}}",
+        latex_column_spec(schema)
+    )?;
+    writeln!(writer, "\\toprule")?;
+    let header = header_cells(schema, &[], format_options, &options)?;
+    print_latex_row(writer, &header)?;
+    writeln!(writer, "\\midrule")?;
+    for batch in batches {
+        for row in format_rows(batch, format_options)? {
+            print_latex_row(writer, &row)?;
+        }
+    }
+    writeln!(writer, "\\bottomrule")?;
+    writeln!(writer, "\\end{{tabular}}")?;
+    Ok(())
+}
+
+/// Write the batches to the [`PrintFormat::Parquet`] file at
+/// [`PrintBatchesOptions::output_path`]
+fn write_parquet(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let Some(path) = &options.output_path else {
+        return exec_err!(
+            "Parquet output cannot be written to stdout, an output path is required"
+        );
+    };
+    let file = create_output_file(path, options.force_overwrite)?;
+    let mut writer = ArrowWriter::try_new(file, Arc::clone(schema), None)?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.into_inner()?.sync_all()?;
+    Ok(())
+}
+
+/// Create the file at `path` to write output to, failing if a file already
+/// exists there unless `force` is set, so earlier exports are not replaced
+/// by accident
+pub fn create_output_file(path: &Path, force: bool) -> Result<std::fs::File> {
+    if force {
+        return Ok(std::fs::File::create(path)?);
+    }
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(file) => Ok(file),
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => exec_err!(
+            "Output file {} already exists, use --force to overwrite it",
+            path.display()
+        ),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Flush the output written to a file from [`create_output_file`], and sync
+/// the file to disk
+pub fn sync_output_file(writer: std::io::BufWriter<std::fs::File>) -> Result<()> {
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Write the batches as an [`PrintFormat::Arrow`] IPC stream, whose schema
+/// message is written even when there are no batches
+fn write_arrow_stream<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<()> {
+    let mut stream_writer = StreamWriter::try_new(writer, schema)?;
+    for batch in batches {
+        stream_writer.write(batch)?;
+    }
+    stream_writer.finish()?;
+    Ok(())
+}
+
+/// An INI key or value, quoted if it contains a line break, a character
+/// special in INI, or leading or trailing whitespace
+fn ini_quote(text: &str) -> String {
+    let special = |c: char| matches!(c, '\n' | '\r' | '=' | ';' | '#' | '[' | ']' | '"');
+    if !text.contains(special) && text.trim() == text {
+        return text.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Print the batches in the [`PrintFormat::Ini`] format, numbering the rows
+/// from 1 across all batches
+fn print_ini<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let mut row_number = 0;
+    for batch in batches {
+        for (row_idx, row) in format_rows(batch, format_options)?.iter().enumerate() {
+            if row_number > 0 {
+                writeln!(writer)?;
+            }
+            row_number += 1;
+            writeln!(writer, "[row {row_number}]")?;
+            for ((field, column), value) in
+                schema.fields().iter().zip(batch.columns()).zip(row)
+            {
+                let key = ini_quote(field.name());
+                if column.is_null(row_idx) {
+                    if !options.ini_omit_nulls {
+                        writeln!(writer, "{key}=")?;
+                    }
+                    continue;
+                }
+                writeln!(writer, "{key}={}", ini_quote(value))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the batches in the [`PrintFormat::JsonWithSchema`] format, with the
+/// data types as arrow displays them
+fn print_json_with_schema<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let fields: Vec<Value> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            json!({
+                "name": field.name(),
+                "type": field.data_type().to_string(),
+            })
+        })
+        .collect();
+    let mut rows: Vec<u8> = vec![];
+    PrintFormat::Json.print_json(&mut rows, batches, options)?;
+    // the writer prints nothing rather than an empty array without rows
+    let rows = match String::from_utf8_lossy(&rows).trim_end() {
+        "" => "[]".to_string(),
+        rows => rows.to_string(),
+    };
+    writeln!(
+        writer,
+        "{{\"schema\":{},\"rows\":{rows}}}",
+        Value::Array(fields)
+    )?;
+    Ok(())
+}
+
+/// Print the batches in the [`PrintFormat::Vertical`] format, numbering the
+/// records from 1 across all batches. The column names are padded to the
+/// widest one, and the lines of multi-line values after the first are
+/// indented to the values.
+fn print_vertical<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+) -> Result<()> {
+    let name_width = schema
+        .fields()
+        .iter()
+        .map(|field| line_width(field.name()))
+        .max()
+        .unwrap_or(0);
+    let mut record_number = 0;
+    for batch in batches {
+        for row in format_rows(batch, format_options)? {
+            record_number += 1;
+            writeln!(writer, "-[ RECORD {record_number} ]-")?;
+            for (field, value) in schema.fields().iter().zip(&row) {
+                let mut name = pad_cell(field.name(), name_width);
+                for line in cell_lines(value) {
+                    writeln!(writer, "{name} | {line}")?;
+                    name = " ".repeat(name_width);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print a horizontal border such as `+---+---+`
+fn print_border<W: std::io::Write>(
+    writer: &mut W,
+    widths: &[usize],
+    line: BorderLine,
+    style: BorderStyle,
+) -> Result<()> {
+    let [left, horizontal, junction, right] = style.horizontal(line);
+    let border = widths
+        .iter()
+        .map(|width| horizontal.to_string().repeat(width + 2))
+        .collect::<Vec<_>>()
+        .join(&junction.to_string());
+    writeln!(writer, "{left}{border}{right}")?;
+    Ok(())
+}
+
+/// The width of a cell spanning columns of `widths`, including the borders
+/// between them
+fn span_width(widths: &[usize]) -> usize {
+    widths.iter().sum::<usize>() + 3 * widths.len().saturating_sub(1)
+}
+
+/// Split the header cells into the groups of
+/// [`PrintBatchesOptions::group_headers_delimiter`], as the name of each group
+/// and the number of adjacent columns it spans, and the rest of each cell.
+/// Columns whose names lack the delimiter each span an unnamed group.
+fn column_groups(
+    header: &[String],
+    delimiter: char,
+) -> (Vec<(String, usize)>, Vec<String>) {
+    let mut groups: Vec<(String, usize)> = vec![];
+    let mut leaves = vec![];
+    for cell in header {
+        // only the name, and not the data type on the following line, is split
+        let (name, rest) = cell
+            .split_once('\n')
+            .map_or((cell.as_str(), None), |(name, rest)| (name, Some(rest)));
+        let (group, leaf) = name.split_once(delimiter).unwrap_or(("", name));
+        let leaf = match rest {
+            Some(rest) => format!("{leaf}\n{rest}"),
+            None => leaf.to_string(),
+        };
+        leaves.push(leaf);
+        match groups.last_mut() {
+            Some((last, len)) if !group.is_empty() && last == group => *len += 1,
+            _ => groups.push((group.to_string(), 1)),
+        }
+    }
+    (groups, leaves)
+}
+
+/// Print the top border, the column names and the border below them, with
+/// the row of [`PrintBatchesOptions::group_headers_delimiter`] groups above
+/// the names if set. Only the top border is printed when `table_header` is
+/// disabled.
+fn print_header<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    ranges: &[Option<String>],
+    widths: &[usize],
+    alignments: &[Alignment],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let style = options.border_style;
+    if !options.table_header {
+        return print_border(writer, widths, BorderLine::Top, style);
+    }
+    let mut header = header_cells(schema, ranges, format_options, options)?;
+    let header_styles = if options.colorize {
+        vec![Some(BOLD); header.len()]
+    } else {
+        vec![]
+    };
+    // the group row, if any, is the top of the table
+    let mut above_header = BorderLine::Top;
+    if let Some(delimiter) = options.group_headers_delimiter {
+        above_header = BorderLine::Separator;
+        let (groups, leaves) = column_groups(&header, delimiter);
+        header = leaves;
+        let mut group_widths = vec![];
+        let mut end = 0;
+        for (_, len) in &groups {
+            end += len;
+            group_widths.push(span_width(&widths[end - len..end]));
+        }
+        let group_names: Vec<String> =
+            groups.into_iter().map(|(group, _)| group).collect();
+        print_border(writer, &group_widths, BorderLine::Top, style)?;
+        print_row(
+            writer,
+            &group_names,
+            &group_widths,
+            &[],
+            None,
+            &header_styles,
+            options,
+        )?;
+    }
+    print_border(writer, widths, above_header, style)?;
+    print_row(
+        writer,
+        &wrap_header_cells(header, widths, options),
+        widths,
+        alignments,
+        None,
+        &header_styles,
+        options,
+    )?;
+    print_border(writer, widths, BorderLine::Separator, style)
+}
+
+/// Print the rows of `batch` padded to `widths`, styling the rows matching
+/// [`PrintBatchesOptions::highlight`], coloring the values of
+/// [`PrintBatchesOptions::categorical_color_columns`], and styling nulls and
+/// numbers if [`PrintBatchesOptions::colorize`] is set. The `decisions` are
+/// kept across the batches of a stream.
+fn print_batch_with_widths<W: std::io::Write>(
+    writer: &mut W,
+    batch: &RecordBatch,
+    widths: &[usize],
+    alignments: &[Alignment],
+    decisions: &mut ColumnDecisions,
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    decisions.update(&batch.schema(), options);
+    let ColumnDecisions {
+        null_typed,
+        numeric,
+        categorical,
+        ..
+    } = &*decisions;
+    let display_options: arrow::util::display::FormatOptions =
+        format_options.try_into()?;
+    let rows =
+        format_rows_with(batch, &display_options, null_typed, &format_options.null)?;
+    for (idx, row) in rows.iter().enumerate() {
+        let style = options
+            .highlight
+            .as_ref()
+            .filter(|highlight| (highlight.predicate)(batch, idx))
+            .map(|highlight| highlight.style.as_str());
+        let cell_styles: Vec<Option<&str>> = row
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| {
+                let is_null = null_typed[col] || batch.column(col).is_null(idx);
+                if categorical[col] && !is_null {
+                    categorical_color(cell, &options.categorical_palette)
+                } else if options.colorize && is_null {
+                    Some(DIM)
+                } else if options.colorize && numeric[col] {
+                    options.numeric_color.as_deref()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        print_row(
+            writer,
+            row,
+            widths,
+            alignments,
+            style,
+            &cell_styles,
+            options,
+        )?;
+    }
+    Ok(())
+}
+
+/// The ANSI SGR parameters of [`PrintBatchesOptions::colorize`] headers
+const BOLD: &str = "1";
+/// The ANSI SGR parameters of [`PrintBatchesOptions::colorize`] nulls
+const DIM: &str = "2";
+
+/// The color of `value` in `palette`, from a hash of the value that is the
+/// same in every run
+fn categorical_color<'a>(value: &str, palette: &'a [String]) -> Option<&'a str> {
+    // 64-bit FNV-1a
+    let hash = value.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    let idx = (hash % palette.len().max(1) as u64) as usize;
+    palette.get(idx).map(String::as_str)
+}
+
+/// Print a line marking that rows were omitted, such as `| .     |`
+fn print_dotted_line<W: std::io::Write>(
+    writer: &mut W,
+    widths: &[usize],
+    style: BorderStyle,
+) -> Result<()> {
+    let border_width: usize = 1 + widths.iter().map(|width| width + 3).sum::<usize>();
+    let spaces = border_width.saturating_sub(4);
+    let vertical = style.vertical();
+    writeln!(writer, "{vertical} .{:<spaces$}{vertical}", "")?;
+    Ok(())
+}
+
+/// Print a single line spanning all columns counting the rows omitted, such
+/// as `| … and 1,000 more rows |`
+fn print_omitted_rows_line<W: std::io::Write>(
+    writer: &mut W,
+    widths: &[usize],
+    omitted_rows: usize,
+    style: BorderStyle,
+) -> Result<()> {
+    let border_width: usize = 1 + widths.iter().map(|width| width + 3).sum::<usize>();
+    let text = format!("… and {} more rows", format_thousands(omitted_rows));
+    let vertical = style.vertical();
+    writeln!(
+        writer,
+        "{vertical} {} {vertical}",
+        pad_cell(&text, border_width.saturating_sub(4))
+    )?;
+    Ok(())
+}
+
+/// Format `n` with `,` separating groups of thousands
+fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Print the border below the last row
+fn print_bottom_border<W: std::io::Write>(
+    writer: &mut W,
+    widths: &[usize],
+    style: BorderStyle,
+) -> Result<()> {
+    print_border(writer, widths, BorderLine::Bottom, style)
+}
+
+fn format_batches_with_maxrows<W: std::io::Write>(
+    writer: &mut W,
+    batches: &[RecordBatch],
+    maxrows: MaxRows,
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    // the header uses the schema of the batches rather than the declared
+    // schema, as any metadata only differences do not matter for display
+    let Some(schema) = batches.first().map(|batch| batch.schema()) else {
+        return Ok(());
+    };
+
+    if let (Some(max_width), true) =
+        (options.max_table_width, options.sticky_first_column)
+    {
+        let segments =
+            sticky_column_segments(&schema, batches, max_width, format_options, options)?;
+        let options = PrintBatchesOptions {
+            max_table_width: None,
+            sticky_first_column: false,
+            ..options.clone()
+        };
+        for segment in segments {
+            let batches = batches
+                .iter()
+                .map(|batch| batch.project(&segment))
+                .collect::<Result<Vec<_>, _>>()?;
+            format_batches_with_maxrows(
+                writer,
+                &batches,
+                maxrows,
+                format_options,
+                &options,
+            )?;
+        }
+        return Ok(());
+    }
+
+    if let Some(max_width) = options.max_table_width {
+        let kept = fit_columns(&schema, batches, max_width, format_options, options)?;
+        if kept.len() < schema.fields().len() {
+            let dropped: Vec<&str> = schema
+                .fields()
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| !kept.contains(idx))
+                .map(|(_, field)| field.name().as_str())
+                .collect();
+            let batches = batches
+                .iter()
+                .map(|batch| batch.project(&kept))
+                .collect::<Result<Vec<_>, _>>()?;
+            let options = PrintBatchesOptions {
+                max_table_width: None,
+                ..options.clone()
+            };
+            format_batches_with_maxrows(
+                writer,
+                &batches,
+                maxrows,
+                format_options,
+                &options,
+            )?;
+            writeln!(writer, "Dropped columns: {}", dropped.join(", "))?;
+            return Ok(());
+        }
+    }
+
+    // buffer all the rows so the column widths fit every value
+    let options = PrintBatchesOptions {
+        maxrows,
+        format_options: format_options.clone(),
+        ..options.clone()
+    };
+    let mut state = OutputStreamState::new(writer, schema, maxrows, format_options)
+        .with_preview_limit(usize::MAX)
+        .with_options(options);
+    for batch in batches {
+        state.process_batch(batch)?;
+    }
+    state.finish()
+}
+
+/// Return the indices of the columns that fit in a table `max_width` wide,
+/// after dropping the lowest [`PrintBatchesOptions::column_priorities`]
+/// columns, rightmost first among equal priorities. At least one column is
+/// always kept.
+fn fit_columns(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    max_width: usize,
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<Vec<usize>> {
+    let prepared = batches
+        .iter()
+        .map(|batch| PrintFormat::Table.prepare_batch(batch, options))
+        .collect::<Result<Vec<_>>>()?;
+    let ranges = column_ranges(schema, batches, options)?;
+    let widths =
+        compute_column_widths(schema, &prepared, &ranges, format_options, options)?;
+
+    let mut kept: Vec<usize> = (0..widths.len()).collect();
+    // each column adds `| ` and ` ` around its cells, and the row ends with `|`
+    let table_width =
+        |kept: &[usize]| kept.iter().map(|&idx| widths[idx] + 3).sum::<usize>() + 1;
+    let priority = |idx: usize| {
+        let name = schema.field(idx).name();
+        options.column_priorities.get(name).copied().unwrap_or(0)
+    };
+    while kept.len() > 1 && table_width(&kept) > max_width {
+        let lowest = kept
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, &idx)| priority(idx))
+            .map(|(position, _)| position)
+            .unwrap();
+        kept.remove(lowest);
+    }
+    Ok(kept)
+}
+
+/// Split the columns into segments that each fit in a table `max_width` wide
+/// where possible, in order, with the first column repeated at the start of
+/// every segment. A column too wide to fit even next to the first column
+/// gets a segment of its own.
+fn sticky_column_segments(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    max_width: usize,
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<Vec<Vec<usize>>> {
+    let prepared = batches
+        .iter()
+        .map(|batch| PrintFormat::Table.prepare_batch(batch, options))
+        .collect::<Result<Vec<_>>>()?;
+    let ranges = column_ranges(schema, batches, options)?;
+    let widths =
+        compute_column_widths(schema, &prepared, &ranges, format_options, options)?;
+    if widths.len() < 2 {
+        return Ok(vec![(0..widths.len()).collect()]);
+    }
+
+    // each column adds `| ` and ` ` around its cells, and the row ends with `|`
+    let first_width = widths[0] + 3 + 1;
+    let mut segments: Vec<Vec<usize>> = vec![];
+    let mut segment = vec![0];
+    let mut segment_width = first_width;
+    for (idx, width) in widths.iter().enumerate().skip(1) {
+        if segment.len() > 1 && segment_width + width + 3 > max_width {
+            segments.push(std::mem::replace(&mut segment, vec![0]));
+            segment_width = first_width;
+        }
+        segment.push(idx);
+        segment_width += width + 3;
+    }
+    segments.push(segment);
+    Ok(segments)
+}
+
+/// Return the `k` most frequent non-null formatted values of each column as
+/// `value (count)` lists, where ties keep the order values are first seen
+fn top_values(
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    k: usize,
+) -> Result<Vec<String>> {
+    let arrow_options: arrow::util::display::FormatOptions = format_options.try_into()?;
+    let mut counts: Vec<HashMap<String, (usize, usize)>> =
+        vec![HashMap::new(); schema.fields().len()];
+    for batch in batches {
+        for (column_counts, column) in counts.iter_mut().zip(batch.columns()) {
+            let formatter = ArrayFormatter::try_new(column.as_ref(), &arrow_options)?;
+            for row in 0..column.len() {
+                if column.is_null(row) {
+                    continue;
+                }
+                let value = formatter.value(row).try_to_string()?;
+                let first_seen = column_counts.len();
+                column_counts.entry(value).or_insert((0, first_seen)).0 += 1;
+            }
+        }
+    }
+
+    Ok(counts
+        .into_iter()
+        .map(|column_counts| {
+            let mut column_counts: Vec<_> = column_counts.into_iter().collect();
+            column_counts.sort_by_key(|(_, (count, first_seen))| {
+                (std::cmp::Reverse(*count), *first_seen)
+            });
+            column_counts
+                .into_iter()
+                .take(k)
+                .map(|(value, (count, _))| format!("{value} ({count})"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect())
+}
+
+/// Print the name, data type and Table display width of each column, where
+/// the widths are computed from `batches`, and the most frequent values if
+/// [`PrintBatchesOptions::top_k`] is set
+fn print_describe<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let ranges = column_ranges(schema, batches, options)?;
+    let widths =
+        compute_column_widths(schema, batches, &ranges, format_options, options)?;
+    let mut describe_fields = vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("data_type", DataType::Utf8, false),
+        Field::new("width", DataType::UInt64, false),
+    ];
+    let fields = schema.fields();
+    let mut describe_columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.name()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            fields.iter().map(|f| f.data_type().to_string()),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            widths.iter().map(|width| *width as u64),
+        )),
+    ];
+    if options.top_k > 0 {
+        describe_fields.push(Field::new("top_values", DataType::Utf8, false));
+        describe_columns.push(Arc::new(StringArray::from_iter_values(top_values(
+            schema,
+            batches,
+            format_options,
+            options.top_k,
+        )?)));
+    }
+    let describe_batch =
+        RecordBatch::try_new(Arc::new(Schema::new(describe_fields)), describe_columns)?;
+
+    // the options of the rows would name columns of the described schema
+    let options = PrintBatchesOptions {
+        border_style: options.border_style,
+        colorize: options.colorize,
+        ..PrintBatchesOptions::default()
+    };
+    format_batches_with_maxrows(
+        writer,
+        &[describe_batch],
+        MaxRows::Unlimited,
+        format_options,
+        &options,
+    )
+}
+
+/// Print the min, median, 95th percentile and max Table display width of the
+/// values of each column, using nearest-rank percentiles. Columns of
+/// `batches` without rows have all widths `0`.
+fn print_width_histogram<W: std::io::Write>(
+    writer: &mut W,
+    schema: &SchemaRef,
+    batches: &[RecordBatch],
+    format_options: &FormatOptions,
+    options: &PrintBatchesOptions,
+) -> Result<()> {
+    let mut lengths: Vec<Vec<usize>> = vec![vec![]; schema.fields().len()];
+    for batch in batches {
+        let batch = PrintFormat::Table.prepare_batch(batch, options)?;
+        for row in format_rows(&batch, format_options)? {
+            for (column_lengths, cell) in lengths.iter_mut().zip(&row) {
+                column_lengths.push(cell_width(cell));
+            }
+        }
+    }
+    for column_lengths in &mut lengths {
+        column_lengths.sort_unstable();
+    }
+
+    let percentile = |percent: usize| {
+        UInt64Array::from_iter_values(lengths.iter().map(|column_lengths| {
+            let rank = (column_lengths.len() * percent).div_ceil(100).max(1);
+            column_lengths.get(rank - 1).copied().unwrap_or(0) as u64
+        }))
+    };
+    let histogram_schema = Arc::new(Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("min", DataType::UInt64, false),
+        Field::new("median", DataType::UInt64, false),
+        Field::new("p95", DataType::UInt64, false),
+        Field::new("max", DataType::UInt64, false),
+    ]));
+    let histogram_batch = RecordBatch::try_new(
+        histogram_schema,
+        vec![
+            Arc::new(StringArray::from_iter_values(
+                schema.fields().iter().map(|f| f.name()),
+            )),
+            Arc::new(percentile(0)),
+            Arc::new(percentile(50)),
+            Arc::new(percentile(95)),
+            Arc::new(percentile(100)),
+        ],
+    )?;
+
+    format_batches_with_maxrows(
+        writer,
+        &[histogram_batch],
+        MaxRows::Unlimited,
+        format_options,
+        &PrintBatchesOptions::default(),
+    )
+}
+
+/// The first `max_rows` rows of `batches`, sliced rather than copied
+fn limit_batches(batches: &[RecordBatch], max_rows: usize) -> Vec<RecordBatch> {
+    let mut remaining = max_rows;
+    batches
+        .iter()
+        .map_while(|batch| {
+            let kept = batch.num_rows().min(remaining);
+            remaining -= kept;
+            (kept > 0).then(|| batch.slice(0, kept))
+        })
+        .collect()
+}
+
+/// The indices of the columns of `schema` named `columns`, in their order
+fn display_projection(schema: &SchemaRef, columns: &[String]) -> Result<Vec<usize>> {
+    columns
+        .iter()
+        .map(|name| match schema.index_of(name) {
+            Ok(idx) => Ok(idx),
+            Err(_) => {
+                let names: Vec<&str> = schema
+                    .fields()
+                    .iter()
+                    .map(|field| field.name().as_str())
+                    .collect();
+                exec_err!(
+                    "Unknown display column '{name}', the columns are: {}",
+                    names.join(", ")
+                )
+            }
+        })
+        .collect()
+}
+
+impl PrintFormat {
+    /// Print the batches to a writer using the specified format, at most
+    /// `maxrows` of their rows
+    #[deprecated(
+        since = "49.0.0",
+        note = "use `print_with_options`, setting `maxrows`, `with_header` and `format_options` in the `PrintBatchesOptions`"
+    )]
+    pub fn print_batches<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        maxrows: MaxRows,
+        with_header: bool,
+        format_options: &FormatOptions,
+    ) -> Result<()> {
+        let options = PrintBatchesOptions {
+            maxrows,
+            with_header,
+            format_options: format_options.clone(),
+            ..Default::default()
+        };
+        self.print_with_options(writer, schema, batches, &options)
+    }
+
+    /// Print the name and data type of each column of `schema` as the rows
+    /// of a `column`, `type` table in this format, with no need for any
+    /// batches, such as to inspect the columns of a query without computing
+    /// its rows. Only the [`PrintBatchesOptions::display_columns`] are
+    /// described.
+    pub fn print_schema<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: &SchemaRef,
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        let schema = match &options.display_columns {
+            Some(columns) => {
+                Arc::new(schema.project(&display_projection(schema, columns)?)?)
+            }
+            None => Arc::clone(schema),
+        };
+        let fields = schema.fields();
+        let schema_batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("column", DataType::Utf8, false),
+                Field::new("type", DataType::Utf8, false),
+            ])),
+            vec![
+                Arc::new(StringArray::from_iter_values(
+                    fields.iter().map(|f| f.name()),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    fields.iter().map(|f| f.data_type().to_string()),
+                )),
+            ],
+        )?;
+
+        // the options of the rows would name columns of the described schema
+        let options = PrintBatchesOptions {
+            with_header: options.with_header,
+            format_options: options.format_options.clone(),
+            border_style: options.border_style,
+            colorize: options.colorize,
+            output_path: options.output_path.clone(),
+            force_overwrite: options.force_overwrite,
+            ..PrintBatchesOptions::default()
+        };
+        self.print_with_options(writer, schema_batch.schema(), &[schema_batch], &options)
+    }
+
+    /// Print the batches to a writer using the specified format, at most
+    /// [`PrintBatchesOptions::maxrows`] of their rows
+    pub fn print_with_options<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        // a trait object, so that the output is printed to a writer of the
+        // same type whatever the writer of this call
+        let writer: &mut dyn std::io::Write = writer;
+        if options.ignore_broken_pipe {
+            let mut writer = PipeWriter::new(writer);
+            let result = self.print_resolved(&mut writer, schema, batches, options);
+            return writer.ignore_broken(result);
+        }
+        self.print_resolved(writer, schema, batches, options)
+    }
+
+    /// Print the batches as [`Self::print_with_options`] does, once the
+    /// options that shape the output as a whole, such as
+    /// [`PrintBatchesOptions::section_label`], are resolved into the lines
+    /// printed around the records and the encoding of the records
+    fn print_resolved(
+        &self,
+        writer: &mut dyn std::io::Write,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        let format = self.for_output_path(options.output_path.as_deref());
+        if matches!(format, Self::Parquet) && options.output_path.is_none() {
+            return exec_err!(
+                "Parquet output cannot be written to stdout, an output path is required"
+            );
+        }
+        // the Parquet file is written to the output path, so nothing is left
+        // to encode
+        if matches!(format, Self::Parquet) && options.base64_wrap {
+            return exec_err!("Parquet output cannot be base64 wrapped");
+        }
+
+        let separated = matches!(format, Self::Csv | Self::Tsv | Self::Automatic);
+        let mut schema = schema;
+        let mut batches = batches.to_vec();
+        if let Some(columns) = &options.display_columns {
+            let projection = display_projection(&schema, columns)?;
+            batches = batches
+                .iter()
+                .map(|batch| batch.project(&projection))
+                .collect::<Result<Vec<_>, _>>()?;
+            schema = Arc::new(schema.project(&projection)?);
+        }
+        if options.batch_index_column && separated {
+            batches = batches
+                .iter()
+                .enumerate()
+                .map(|(index, batch)| with_batch_index(batch, index))
+                .collect::<Result<Vec<_>>>()?;
+            schema = batch_index_schema(&schema);
+        }
+        let utf16 = options.encoding == OutputEncoding::Utf16Le && separated;
+        let preset = match format {
+            Self::Tsv => options.data_science_preset.as_ref(),
+            _ => None,
+        };
+
+        // the records are printed with the options left once the output as
+        // a whole is taken care of here
+        let mut records_options = PrintBatchesOptions {
+            ignore_broken_pipe: false,
+            display_columns: None,
+            csv_bom: false,
+            section_label: None,
+            base64_wrap: false,
+            encoding: OutputEncoding::Utf8,
+            batch_index_column: false,
+            row_count_comment: false,
+            ..options.clone()
+        };
+        if preset.is_some() {
+            records_options = records_options.without_data_science_preset();
+        }
+
+        // a base64 wrapped output or one encoded as UTF-16 starts with the
+        // mark of its payload or its own
+        if options.csv_bom
+            && !options.base64_wrap
+            && options.encoding == OutputEncoding::Utf8
+            && separated
+        {
+            writer.write_all(UTF8_BOM)?;
+        }
+        let prefix = format.comment_prefix().unwrap_or_default();
+        if let Some(label) = &options.section_label {
+            writeln!(writer, "{prefix}=== {label} ===")?;
+        }
+
+        // base64 wrapped and UTF-16 output is encoded as a whole
+        let mut buffer: Vec<u8> = vec![];
+        let mut records: &mut dyn std::io::Write = if options.base64_wrap || utf16 {
+            &mut buffer
+        } else {
+            &mut *writer
+        };
+        if let Some(preset) = preset {
+            print_column_types(&mut records, preset, &schema)?;
+        }
+        format.print_records(
+            &mut records,
+            Arc::clone(&schema),
+            &batches,
+            &records_options,
+        )?;
+        if options.row_count_comment && separated {
+            let rows = batches.iter().map(|batch| batch.num_rows()).sum::<usize>();
+            let rows = match options.maxrows {
+                MaxRows::Limited(maxrows) => rows.min(maxrows),
+                MaxRows::Unlimited => rows,
+            };
+            format.print_row_count_comment(&mut records, rows)?;
+        }
+
+        if utf16 {
+            // the writers produce UTF-8, so the whole output is transcoded
+            buffer = encode_utf16le(&String::from_utf8_lossy(&buffer));
+        }
+        if options.base64_wrap {
+            writeln!(writer, "base64:{}", format.output_name())?;
+            let encoded = STANDARD.encode(&buffer);
+            // base64 is ASCII, so splitting the bytes splits the characters
+            let line_width = options.base64_line_width.unwrap_or(usize::MAX).max(1);
+            for line in encoded.as_bytes().chunks(line_width) {
+                writer.write_all(line)?;
+                writeln!(writer)?;
+            }
+        } else if utf16 {
+            writer.write_all(&buffer)?;
+        }
+
+        if let Some(label) = &options.section_label {
+            writeln!(writer, "{prefix}=== end {label} ===")?;
+        }
+        Ok(())
+    }
+
+    /// Print the records of the batches in this format, with the options
+    /// shaping the output as a whole resolved, see [`Self::print_resolved`]
+    fn print_records<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        // filter out any empty batches
+        let batches: Vec<_> = batches
+            .iter()
+            .filter(|b| b.num_rows() > 0)
+            .cloned()
+            .collect();
+        if options.describe {
+            return print_describe(
+                writer,
+                &schema,
+                &batches,
+                &options.format_options,
+                options,
+            );
+        }
+        if options.width_histogram {
+            return print_width_histogram(
+                writer,
+                &schema,
+                &batches,
+                &options.format_options,
+                options,
+            );
+        }
+        // Table marks that rows were omitted, Pivot limits the rows of the
+        // cross-tab rather than its input, and the binary exports keep every
+        // row
+        let batches = match (self, options.maxrows) {
+            (Self::Table | Self::Pivot | Self::Parquet | Self::Arrow, _)
+            | (_, MaxRows::Unlimited) => batches,
+            (_, MaxRows::Limited(maxrows)) => limit_batches(&batches, maxrows),
+        };
+        if options.descriptor_line {
+            self.print_descriptor_line(writer, &schema)?;
+        }
+        if let Some(sql) = &options.source_sql {
+            self.print_source_sql(writer, sql)?;
+        }
+        if batches.is_empty() {
+            return self.print_empty(writer, schema, options);
+        }
+
+        // Table prepares each batch as it is printed, see `OutputStreamState`,
+        // and the binary formats keep the values unformatted
+        let batches = match self {
+            Self::Table | Self::Parquet | Self::Arrow => batches,
+            _ => batches
+                .iter()
+                .map(|batch| self.prepare_batch(batch, options))
+                .collect::<Result<Vec<_>>>()?,
+        };
+
+        match self {
+            Self::Csv | Self::Automatic => {
+                print_batches_with_sep(writer, &batches, b',', options)
+            }
+            Self::Tsv => print_batches_with_sep(writer, &batches, b'\t', options),
+            Self::Table => format_batches_with_maxrows(
+                writer,
+                &batches,
+                options.maxrows,
+                &options.format_options,
+                options,
+            ),
+            Self::Json | Self::NdJson | Self::JsonPretty => {
+                self.print_json(writer, &batches, options)
+            }
+            Self::JsonWithSchema => {
+                print_json_with_schema(writer, &schema, &batches, options)
+            }
+            Self::Markdown => print_markdown(
+                writer,
+                &schema,
+                &batches,
+                &options.format_options,
+                options,
+            ),
+            Self::Html => {
+                print_html(writer, &schema, &batches, &options.format_options, options)
+            }
+            Self::Logfmt => {
+                print_logfmt(writer, &schema, &batches, &options.format_options, options)
+            }
+            Self::Latex => {
+                print_latex(writer, &schema, &batches, &options.format_options, options)
+            }
+            Self::Parquet => write_parquet(&schema, &batches, options),
+            // the schema is always written, so `with_header` does not apply to
+            // this binary format
+            Self::Arrow => write_arrow_stream(writer, &schema, &batches),
+            Self::Ini => {
+                print_ini(writer, &schema, &batches, &options.format_options, options)
+            }
+            Self::Vertical => {
+                print_vertical(writer, &schema, &batches, &options.format_options)
+            }
+            Self::FixedWidth => print_fixed_width(
+                writer,
+                &schema,
+                &batches,
+                options.with_header,
+                &options.format_options,
+                options,
+            ),
+            Self::Pivot => {
+                let Some(pivot) = &options.pivot else {
+                    return exec_err!(
+                        "PrintFormat::Pivot requires the pivot columns to be configured"
+                    );
+                };
+                let pivoted =
+                    pivot_batches(&batches, &schema, pivot, &options.format_options)?;
+                format_batches_with_maxrows(
+                    writer,
+                    &[pivoted],
+                    options.maxrows,
+                    &options.format_options,
+                    options,
+                )
+            }
+        }
+    }
+
+    /// Return the headers, column widths and formatted values the
+    /// [`Self::Table`] format would print for the batches, without rendering
+    /// them
+    pub fn layout(
+        &self,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        options: &PrintBatchesOptions,
+    ) -> Result<TableLayout> {
+        let format_options = &options.format_options;
+        let ranges = column_ranges(&schema, batches, options)?;
+        let batches = batches
+            .iter()
+            .map(|batch| self.prepare_batch(batch, options))
+            .collect::<Result<Vec<_>>>()?;
+        let widths =
+            compute_column_widths(&schema, &batches, &ranges, format_options, options)?;
+
+        let mut rows = vec![];
+        for batch in &batches {
+            rows.extend(format_rows(batch, format_options)?);
+        }
+
+        Ok(TableLayout {
+            headers: header_cells(&schema, &ranges, format_options, options)?,
+            widths,
+            rows,
+        })
+    }
+
+    /// Write the batches into `dir` in this format, one file per distinct
+    /// value of the partition column, named by the value. Rows with a null
+    /// value are written to `__null__`. Existing files are only replaced with
+    /// [`PrintBatchesOptions::force_overwrite`]. Returns the paths of the
+    /// written files, in the order their values are first seen.
+    pub fn write_partitioned(
+        &self,
+        dir: &Path,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        partition: &PartitionColumn,
+        options: &PrintBatchesOptions,
+    ) -> Result<Vec<PathBuf>> {
+        let partition_idx = schema.index_of(&partition.name)?;
+        let projection: Vec<usize> = (0..schema.fields().len())
+            .filter(|idx| partition.keep || *idx != partition_idx)
+            .collect();
+        let output_schema = Arc::new(schema.project(&projection)?);
+
+        let arrow_options: arrow::util::display::FormatOptions =
+            (&options.format_options).try_into()?;
+        let mut values: Vec<Option<String>> = vec![];
+        let mut groups: HashMap<Option<String>, Vec<RecordBatch>> = HashMap::new();
+        for batch in batches {
+            let column = batch.column(partition_idx);
+            let formatter = ArrayFormatter::try_new(column.as_ref(), &arrow_options)?;
+            let mut rows: HashMap<Option<String>, Vec<u64>> = HashMap::new();
+            let mut batch_values: Vec<Option<String>> = vec![];
+            for row in 0..batch.num_rows() {
+                let value = if column.is_null(row) {
+                    None
+                } else {
+                    Some(formatter.value(row).try_to_string()?)
+                };
+                if !rows.contains_key(&value) {
+                    batch_values.push(value.clone());
+                }
+                rows.entry(value).or_default().push(row as u64);
+            }
+
+            let batch = batch.project(&projection)?;
+            for value in batch_values {
+                let indices = UInt64Array::from(rows.remove(&value).unwrap_or_default());
+                let group = take_record_batch(&batch, &indices)?;
+                if !groups.contains_key(&value) {
+                    values.push(value.clone());
+                }
+                groups.entry(value).or_default().push(group);
+            }
+        }
+
+        std::fs::create_dir_all(dir)?;
+        let mut paths: Vec<PathBuf> = vec![];
+        for value in values {
+            let file_name = format!(
+                "{}.{}",
+                partition_file_stem(value.as_deref()),
+                self.output_name()
+            );
+            let path = dir.join(file_name);
+            if paths.contains(&path) {
+                return exec_err!(
+                    "Cannot partition by '{}': several values are written to {}",
+                    partition.name,
+                    path.display()
+                );
+            }
+            let mut file = create_output_file(&path, options.force_overwrite)?;
+            // Parquet reopens the file just created
+            let options = PrintBatchesOptions {
+                output_path: Some(path.clone()),
+                force_overwrite: true,
+                maxrows: MaxRows::Unlimited,
+                with_header: true,
+                ..options.clone()
+            };
+            self.print_with_options(
+                &mut file,
+                Arc::clone(&output_schema),
+                &groups[&value],
+                &options,
+            )?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Return what [`Self::print_with_options`] prints with the default format
+    /// and print options, for the formats that print text
+    pub fn format_batches_to_string(
+        &self,
+        schema: SchemaRef,
+        batches: &[RecordBatch],
+        maxrows: MaxRows,
+        with_header: bool,
+    ) -> Result<String> {
+        let mut buffer: Vec<u8> = vec![];
+        self.print_with_options(
+            &mut buffer,
+            schema,
+            batches,
+            &PrintBatchesOptions::new()
+                .with_maxrows(maxrows)
+                .with_header(with_header),
+        )?;
+        match String::from_utf8(buffer) {
+            Ok(output) => Ok(output),
+            Err(_) => exec_err!("{} output is not text", self.output_name()),
+        }
+    }
+
+    /// The format of files with the extension of `path`, or `None` if the
+    /// extension is missing or not one of a format
+    pub fn from_extension(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "json" => Some(Self::Json),
+            "ndjson" | "jsonl" => Some(Self::NdJson),
+            "parquet" => Some(Self::Parquet),
+            "arrow" => Some(Self::Arrow),
+            _ => None,
+        }
+    }
+
+    /// The format of the extension of `output_path` for [`Self::Automatic`],
+    /// if it has one, otherwise this format
+    fn for_output_path(self, output_path: Option<&Path>) -> Self {
+        match (self, output_path.and_then(Self::from_extension)) {
+            (Self::Automatic, Some(format)) => format,
+            _ => self,
+        }
+    }
+
+    /// The name of the format of the output, where [`Self::Automatic`]
+    /// prints CSV
+    pub(crate) fn output_name(&self) -> &'static str {
+        match self {
+            Self::Csv | Self::Automatic => "csv",
+            Self::Tsv => "tsv",
+            Self::Table => "table",
+            Self::Json => "json",
+            Self::NdJson => "ndjson",
+            Self::JsonPretty => "json_pretty",
+            Self::FixedWidth => "fixed_width",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+            Self::Logfmt => "logfmt",
+            Self::Latex => "latex",
+            Self::Parquet => "parquet",
+            Self::Arrow => "arrow",
+            Self::Ini => "ini",
+            Self::Vertical => "vertical",
+            Self::JsonWithSchema => "json_with_schema",
+            Self::Pivot => "pivot",
+        }
+    }
+
+    /// The prefix of a comment line, or `None` if the format has no comments
+    fn comment_prefix(&self) -> Option<&'static str> {
+        match self {
+            Self::Json | Self::NdJson | Self::JsonPretty | Self::JsonWithSchema => None,
+            _ => Some("# "),
+        }
+    }
+
+    /// Print `sql` in comment lines, unless the format has no comments or is
+    /// binary
+    fn print_source_sql<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        sql: &str,
+    ) -> Result<()> {
+        let prefix = match (self, self.comment_prefix()) {
+            (Self::Parquet | Self::Arrow, _) | (_, None) => return Ok(()),
+            (_, Some(prefix)) => prefix,
+        };
+        for (idx, line) in sql.trim().lines().enumerate() {
+            let label = if idx == 0 { "sql: " } else { "     " };
+            writeln!(writer, "{prefix}{label}{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Print a `# N rows` comment line
+    fn print_row_count_comment<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        rows: usize,
+    ) -> Result<()> {
+        let prefix = self.comment_prefix().unwrap_or_default();
+        let noun = if rows == 1 { "row" } else { "rows" };
+        writeln!(writer, "{prefix}{rows} {noun}")?;
+        Ok(())
+    }
+
+    /// Print a comment line describing the format and the schema as compact
+    /// JSON, unless the format has no comments or is binary
+    fn print_descriptor_line<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: &SchemaRef,
+    ) -> Result<()> {
+        let prefix = match (self, self.comment_prefix()) {
+            (Self::Parquet | Self::Arrow, _) | (_, None) => return Ok(()),
+            (_, Some(prefix)) => prefix,
+        };
+        let fields: Vec<Value> = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                json!({
+                    "name": field.name(),
+                    "type": field.data_type().to_string(),
+                    "nullable": field.is_nullable(),
+                })
+            })
+            .collect();
+        let descriptor = json!({ "format": self.output_name(), "schema": fields });
+        writeln!(writer, "{prefix}{descriptor}")?;
+        Ok(())
+    }
+
+    /// Apply the options that change the values of `batch` before they are
+    /// formatted
+    fn prepare_batch(
+        &self,
+        batch: &RecordBatch,
+        options: &PrintBatchesOptions,
+    ) -> Result<RecordBatch> {
+        let mut batch = repair_utf8_batch(batch, options.invalid_utf8)?;
+        if let Some(form) = options.normalize_unicode {
+            batch = normalize_batch(&batch, form)?;
+        }
+        let is_json = matches!(
+            self,
+            Self::Json | Self::NdJson | Self::JsonPretty | Self::JsonWithSchema
+        );
+        if !is_json {
+            batch = render_types_batch(&batch, &options.type_renderers)?;
+            batch = encode_binary_batch(&batch, options.binary_encoding)?;
+        }
+        // the decimal columns are strings once their precision is applied
+        let numeric: Vec<bool> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| {
+                field.data_type().is_integer()
+                    || matches!(
+                        field.data_type(),
+                        DataType::Decimal128(..) | DataType::Decimal256(..)
+                    )
+            })
+            .collect();
+        if (options.float_precision.is_some() || options.float_scientific) && !is_json {
+            batch = format_floats_batch(
+                &batch,
+                options.float_precision,
+                options.float_scientific,
+            )?;
+        }
+        if let (Some(separator), Self::Table) = (options.group_digits, self) {
+            batch = group_digits_batch(&batch, &numeric, separator)?;
+        }
+        if options.sanitize_formulas
+            && matches!(self, Self::Csv | Self::Tsv | Self::Automatic)
+        {
+            batch = sanitize_formulas_batch(&batch)?;
+        }
+        if options.empty_string_as_null && is_json {
+            batch = empty_strings_to_null_batch(&batch)?;
+        }
+        if let (Some(tokens), false) = (&options.bool_tokens, is_json) {
+            batch = bool_tokens_batch(&batch, tokens)?;
+        }
+        if matches!(self, Self::Markdown) {
+            batch = map_string_columns(&batch, escape_markdown)?;
+        }
+        if options.tsv_escape && matches!(self, Self::Tsv) {
+            batch = map_string_columns(&batch, |field| {
+                escape_tsv(field, options.tsv_escape_backslashes)
+            })?;
+        }
+        Ok(batch)
+    }
+
+    /// Print the batches in the [`Self::Json`], [`Self::NdJson`] or
+    /// [`Self::JsonPretty`] format
+    fn print_json<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        batches: &[RecordBatch],
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        if options.json_ascii_escape {
+            // non-ASCII characters only appear within JSON strings, so
+            // escaping the whole output is the same as escaping each string
+            let mut buffer: Vec<u8> = vec![];
+            let options = PrintBatchesOptions {
+                json_ascii_escape: false,
+                ..options.clone()
+            };
+            self.print_json(&mut buffer, batches, &options)?;
+            write!(
+                writer,
+                "{}",
+                escape_json_non_ascii(&String::from_utf8_lossy(&buffer))
+            )?;
+            return Ok(());
+        }
+
+        if options.json_nan_policy != JsonNanPolicy::Null {
+            let mut count = 0;
+            let batches = batches
+                .iter()
+                .map(|batch| -> Result<RecordBatch> {
+                    let (replaced, batch) = non_finite_to_null_batch(batch)?;
+                    count += replaced;
+                    Ok(batch)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if count > 0 && options.json_nan_policy == JsonNanPolicy::Error {
+                return exec_err!(
+                    "{count} NaN or infinite values cannot be represented in JSON"
+                );
+            }
+            if count > 0 {
+                options.warnings.warn(format!(
+                    "{count} NaN or infinite values were printed as null"
+                ));
+            }
+            let options = PrintBatchesOptions {
+                json_nan_policy: JsonNanPolicy::Null,
+                ..options.clone()
+            };
+            return self.print_json(writer, &batches, &options);
+        }
+
+        // the writer omits null values, unless empty strings are printed as
+        // nulls, which must not then disappear
+        let explicit_nulls = options.empty_string_as_null;
+        match self {
+            Self::NdJson => {
+                let mut buffer: Vec<u8> = vec![];
+                batches_to_json!(
+                    LineDelimitedWriter,
+                    &mut buffer,
+                    batches,
+                    explicit_nulls
+                )?;
+                // make the final newline explicit rather than relying on
+                // the arrow writer
+                let records = buffer.strip_suffix(b"\n").unwrap_or(&buffer);
+                writer.write_all(records)?;
+                if options.ndjson_trailing_newline && !records.is_empty() {
+                    writeln!(writer)?;
+                }
+                Ok(())
+            }
+            Self::JsonPretty => {
+                let mut buffer: Vec<u8> = vec![];
+                batches_to_json!(ArrayWriter, &mut buffer, batches, explicit_nulls)?;
+                write!(writer, "{}", indent_json(&String::from_utf8_lossy(&buffer)))?;
+                Ok(())
+            }
+            _ => batches_to_json!(ArrayWriter, writer, batches, explicit_nulls),
+        }
+    }
+
+    /// Print when the result batches contain no rows: the header of the text
+    /// formats, an empty JSON array, or nothing for a schema without columns
+    fn print_empty<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+        schema: SchemaRef,
+        options: &PrintBatchesOptions,
+    ) -> Result<()> {
+        let format_options = &options.format_options;
+        match self {
+            Self::Csv | Self::Tsv | Self::Automatic
+                if options.with_header
+                    && options.header_on_empty
+                    && !schema.fields().is_empty() =>
+            {
+                let delimiter = if matches!(self, Self::Tsv) {
+                    b'\t'
+                } else {
+                    b','
+                };
+                print_batches_with_sep(
+                    writer,
+                    &[RecordBatch::new_empty(schema)],
+                    delimiter,
+                    options,
+                )?;
+            }
+            // Print column headers for Table format
+            Self::Table => {
+                OutputStreamState::new(
+                    &mut *writer,
+                    schema,
+                    MaxRows::Unlimited,
+                    format_options,
+                )
+                .with_options(options.clone())
+                .finish()?;
+            }
+            Self::Markdown => {
+                print_markdown(writer, &schema, &[], format_options, options)?;
+            }
+            Self::Html => {
+                print_html(writer, &schema, &[], format_options, options)?;
+            }
+            Self::Latex => {
+                print_latex(writer, &schema, &[], format_options, options)?;
+            }
+            Self::Parquet => write_parquet(&schema, &[], options)?,
+            Self::Arrow => write_arrow_stream(writer, &schema, &[])?,
+            Self::JsonWithSchema => {
+                print_json_with_schema(writer, &schema, &[], options)?
+            }
+            // the records of NdJson are its lines, so no lines are no records
+            Self::Json | Self::JsonPretty if !schema.fields().is_empty() => {
+                writeln!(writer, "[]")?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use arrow::array::{
+        BinaryArray, BooleanArray, Decimal128Array, Float64Array, Int32Array, Int64Array,
+        NullArray, TimestampNanosecondArray,
+    };
+    use arrow::buffer::{Buffer, OffsetBuffer};
+    use arrow::datatypes::{Int32Type, TimeUnit, TimestampNanosecondType};
+    use arrow::ipc::reader::StreamReader;
+    use datafusion::common::assert_contains;
+    use insta::{allow_duplicates, assert_snapshot};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn print_header_on_empty() {
+        for (format, expected) in [
+            (PrintFormat::Csv, "a,b,c\n"),
+            (PrintFormat::Tsv, "a\tb\tc\n"),
+        ] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(vec![RecordBatch::new_empty(three_column_schema())])
+                .with_header(WithHeader::Yes)
+                .with_options(PrintBatchesOptions::new().with_header_on_empty(true))
+                .run();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn print_empty() {
+        for (format, expected) in [
+            (PrintFormat::Csv, "a,b,c\n"),
+            (PrintFormat::Tsv, "a\tb\tc\n"),
+            (PrintFormat::Automatic, "a,b,c\n"),
+            (PrintFormat::Json, "[]\n"),
+            (PrintFormat::JsonPretty, "[]\n"),
+            (PrintFormat::NdJson, ""),
+        ] {
+            // the header of empty batches only with header set
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(vec![RecordBatch::new_empty(three_column_schema())])
+                .with_header(WithHeader::Yes)
+                .run();
+            assert_eq!(output, expected, "{format}");
+        }
+
+        for format in [PrintFormat::Csv, PrintFormat::Tsv, PrintFormat::NdJson] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(vec![])
+                .with_header(WithHeader::No)
+                .run();
+            assert_eq!(output, "", "{format}");
+
+            // nor with the header on empty unset
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(vec![])
+                .with_options(PrintBatchesOptions::new().with_header_on_empty(false))
+                .run();
+            assert_eq!(output, "", "{format}");
+        }
+
+        // no output for a schema without columns
+        for format in [PrintFormat::Csv, PrintFormat::Json, PrintFormat::NdJson] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_batches(vec![])
+                .run();
+            assert_eq!(output, "", "{format}");
+        }
+
+        // output column headers for empty batches when format is Table
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(three_column_schema())
+            .with_batches(vec![])
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        +---+---+---+
+        "#);
+    }
+
+    #[test]
+    fn print_csv_no_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::No)
+            .run();
+        assert_snapshot!(output, @r#"
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+    }
+
+    #[test]
+    fn print_csv_with_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .run();
+        assert_snapshot!(output, @r#"
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+    }
+
+    #[test]
+    fn print_csv_descriptor_line() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_descriptor_line(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        # {"format":"csv","schema":[{"name":"a","nullable":false,"type":"Int32"},{"name":"b","nullable":false,"type":"Int32"},{"name":"c","nullable":false,"type":"Int32"}]}
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+    }
+
+    #[test]
+    fn print_json_descriptor_line() {
+        // JSON has no comments, so the output stays a single JSON array
+        for format in [PrintFormat::Json, PrintFormat::JsonPretty] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(split_batch(three_column_batch()))
+                .with_options(PrintBatchesOptions::new().with_descriptor_line(true))
+                .run();
+            let value: Value = serde_json::from_str(&output).unwrap();
+            assert_eq!(value.as_array().unwrap().len(), 3);
+        }
+    }
+
+    #[test]
+    fn print_parquet_descriptor_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.parquet");
+
+        let mut output: Vec<u8> = vec![];
+        PrintFormat::Parquet
+            .print_with_options(
+                &mut output,
+                three_column_schema(),
+                &[three_column_batch()],
+                &PrintBatchesOptions::new()
+                    .with_output_path(Some(path.clone()))
+                    .with_descriptor_line(true),
+            )
+            .unwrap();
+        assert!(output.is_empty());
+
+        let file = std::fs::File::open(&path).unwrap();
+        let rows = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum::<usize>();
+        assert_eq!(rows, 3);
+    }
+
+    #[test]
+    fn print_csv_source_sql() {
+        let sql = "SELECT a, b, c\nFROM t";
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_source_sql(Some(sql.into())))
+            .run();
+        assert_snapshot!(output, @r#"
+        # sql: SELECT a, b, c
+        #      FROM t
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+
+        // JSON has no comments, so the SQL is left out
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_schema(one_column_schema())
+            .with_batches(vec![one_column_batch()])
+            .with_options(PrintBatchesOptions::new().with_source_sql(Some(sql.into())))
+            .run();
+        assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+    }
+
+    #[test]
+    fn print_pretty_csv() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("id", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["alice", "bob, jr", "x,y"])),
+                Arc::new(Int32Array::from(vec![10, 22, 33])),
+            ],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_pretty_csv(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        name     ,id
+        alice    ,10
+        "bob, jr",22
+        "x,y    ",33
+        "#);
+    }
+
+    #[test]
+    fn print_csv_quote_style() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("id", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![Some("o'brien"), None])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_csv_quote('\''))
+            .run();
+        assert_snapshot!(output, @r#"
+        name,id
+        'o''brien',1
+        ,2
+        "#);
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_csv_quote('\'')
+                    .with_csv_quote_style(QuoteStyle::Always),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        'name','id'
+        'o''brien','1'
+        '','2'
+        "#);
+    }
+
+    #[test]
+    fn print_csv_trailing_delimiter() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_trailing_delimiter(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        a,b,c,
+        1,4,7,
+        2,5,8,
+        3,6,9,
+        "#);
+        assert!(output.lines().all(|line| line.ends_with(',')));
+
+        // a line break within a field quoted with another quote character
+        // does not end the record
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec!["a\nb", "\"c"]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_trailing_delimiter(true)
+                    .with_csv_quote('\''),
+            )
+            .run();
+        assert_eq!(output, "s,\n'a\nb',\n\"c,\n");
+    }
+
+    #[test]
+    fn print_tsv_escapes_tabs_and_newlines() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["a\tb", "c\r\nd", "e\\f"])),
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Tsv)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::Yes)
+            .run();
+        assert_eq!(output, "s\tn\na\\tb\t1\nc\\r\\nd\t2\ne\\f\t3\n");
+        // every line has exactly the two fields
+        assert!(output.lines().all(|line| line.split('\t').count() == 2));
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Tsv)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::No)
+            .with_options(PrintBatchesOptions::new().with_tsv_escape_backslashes(true))
+            .run();
+        assert_eq!(output, "a\\tb\t1\nc\\r\\nd\t2\ne\\\\f\t3\n");
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Tsv)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::No)
+            .with_options(PrintBatchesOptions::new().with_tsv_escape(false))
+            .run();
+        assert_eq!(output, "\"a\tb\"\t1\n\"c\r\nd\"\t2\ne\\f\t3\n");
+    }
+
+    #[test]
+    fn print_tsv_data_science_preset() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![Some("x"), None, Some("y\r\nz")])),
+            ],
+        )
+        .unwrap();
+        let format_options = FormatOptions {
+            null: "NULL".to_string(),
+            ..FormatOptions::default()
+        };
+        let mut output: Vec<u8> = vec![];
+        PrintFormat::Tsv
+            .print_with_options(
+                &mut output,
+                schema,
+                &[batch],
+                &PrintBatchesOptions::new()
+                    .with_data_science_preset(Some(DataSciencePreset::default()))
+                    .with_format_options(format_options.clone()),
+            )
+            .unwrap();
+        // nulls are empty, whatever the format options say
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "# col types: Int32\tUtf8\na\tb\n1\tx\n2\t\n3\ty\\r\\nz\n"
+        );
+    }
+
+    #[test]
+    fn print_stream_tsv_data_science_preset() {
+        // the comment line precedes the first batch only, whether or not the
+        // arrow writer prints the rows
+        for quote_style in [QuoteStyle::Necessary, QuoteStyle::Always] {
+            let mut output: Vec<u8> = vec![];
+            let mut state = OutputStreamState::new(
+                &mut output,
+                three_column_schema(),
+                MaxRows::Unlimited,
+                &FormatOptions::default(),
+            )
+            .with_format(PrintFormat::Tsv)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_csv_bom(true)
+                    .with_csv_quote_style(quote_style)
+                    .with_data_science_preset(Some(DataSciencePreset::default())),
+            );
+            for batch in split_batch(three_column_batch()) {
+                state.process_batch(&batch).unwrap();
+            }
+            state.finish().unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output.matches("# col types").count(), 1, "{quote_style:?}");
+            assert!(output.starts_with("\u{FEFF}# col types: Int32\tInt32\tInt32\n"));
+            // the comment, the header and the rows
+            assert_eq!(output.lines().count(), 5, "{output}");
+        }
+    }
+
+    #[test]
+    fn print_csv_row_count_comment() {
+        for (max_rows, expected) in [
+            (MaxRows::Unlimited, "a,b,c\n1,4,7\n2,5,8\n3,6,9\n# 3 rows\n"),
+            (MaxRows::Limited(1), "a,b,c\n1,4,7\n# 1 row\n"),
+        ] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Csv)
+                .with_batches(split_batch(three_column_batch()))
+                .with_maxrows(max_rows)
+                .with_header(WithHeader::Yes)
+                .with_options(PrintBatchesOptions::new().with_row_count_comment(true))
+                .run();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn print_float_precision() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("f", DataType::Float64, true),
+            Field::new("d", DataType::Decimal128(10, 4), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Float64Array::from(vec![
+                    Some(3.14659265),
+                    Some(1234.5),
+                    None,
+                ])),
+                Arc::new(
+                    Decimal128Array::from(vec![Some(31416), Some(-25), None])
+                        .with_precision_and_scale(10, 4)
+                        .unwrap(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_options(PrintBatchesOptions::new().with_float_precision(Some(2)))
+            .run();
+        assert_snapshot!(output, @r"
+        +---------+------+
+        |       f |    d |
+        +---------+------+
+        |    3.15 | 3.14 |
+        | 1234.50 | 0.00 |
+        |         |      |
+        +---------+------+
+        ");
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::Yes)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_float_precision(Some(1))
+                    .with_float_scientific(true),
+            )
+            .run();
+        assert_eq!(output, "f,d\n3.1e0,3.1e0\n1.2e3,-2.5e-3\n,\n");
+
+        // JSON keeps the numbers
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_float_precision(Some(2)))
+            .run();
+        assert_contains!(output, r#"{"f":3.14659265,"#);
+
+        // the integer digits of a wide decimal leave no room for decimal
+        // places, but are kept rather than printed as null
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "d",
+            DataType::Decimal128(38, 0),
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(
+                Decimal128Array::from(vec![1234567890123456789012345678901234567])
+                    .with_precision_and_scale(38, 0)
+                    .unwrap(),
+            )],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_float_precision(Some(2)))
+            .run();
+        assert_eq!(output, "d\n1234567890123456789012345678901234567\n");
+    }
+
+    #[test]
+    fn print_table_group_digits() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int64, true),
+            Field::new("d", DataType::Decimal128(12, 2), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1234567), Some(-1000), None])),
+                Arc::new(
+                    Decimal128Array::from(vec![Some(123456789), Some(99), None])
+                        .with_precision_and_scale(12, 2)
+                        .unwrap(),
+                ),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_options(PrintBatchesOptions::new().with_group_digits(Some(',')))
+            .run();
+        assert_snapshot!(output, @r"
+        +-----------+--------------+
+        |         n |            d |
+        +-----------+--------------+
+        | 1,234,567 | 1,234,567.89 |
+        |    -1,000 |         0.99 |
+        |           |              |
+        +-----------+--------------+
+        ");
+
+        // CSV keeps machine readable numbers
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_group_digits(Some(',')))
+            .run();
+        assert_eq!(output, "n,d\n1234567,1234567.89\n-1000,0.99\n,\n");
+    }
+
+    #[test]
+    fn print_binary_encoding() {
+        let schema = Arc::new(Schema::new(vec![Field::new("b", DataType::Binary, true)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(BinaryArray::from(vec![
+                Some(b"hi!".as_slice()),
+                Some([0x00, 0xff].as_slice()),
+                None,
+            ]))],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_options(
+                PrintBatchesOptions::new().with_binary_encoding(BinaryEncoding::Base64),
+            )
+            .run();
+        assert_snapshot!(output, @r"
+        +------+
+        | b    |
+        +------+
+        | aGkh |
+        | AP8= |
+        |      |
+        +------+
+        ");
+
+        // the csv writer quotes the empty field of a lone null
+        for (encoding, expected) in [
+            (BinaryEncoding::Hex, "b\n686921\n00ff\n\"\"\n"),
+            (BinaryEncoding::Base64, "b\naGkh\nAP8=\n\"\"\n"),
+        ] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Csv)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(vec![batch.clone()])
+                .with_header(WithHeader::Yes)
+                .with_options(PrintBatchesOptions::new().with_binary_encoding(encoding))
+                .run();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn format_batches_to_string() {
+        let output = PrintFormat::Csv
+            .format_batches_to_string(
+                three_column_schema(),
+                &split_batch(three_column_batch()),
+                MaxRows::Limited(2),
+                true,
+            )
+            .unwrap();
+        assert_eq!(output, "a,b,c\n1,4,7\n2,5,8\n");
+
+        let err = PrintFormat::Arrow
+            .format_batches_to_string(
+                three_column_schema(),
+                &[three_column_batch()],
+                MaxRows::Unlimited,
+                true,
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "arrow output is not text");
+    }
+
+    #[test]
+    fn print_format_display_round_trip() {
+        use clap::ValueEnum;
+        for format in PrintFormat::value_variants() {
+            let name = format.to_string();
+            assert_eq!(name.parse::<PrintFormat>(), Ok(*format), "{name}");
+        }
+        assert_eq!(PrintFormat::NdJson.to_string(), "nd-json");
+        assert_eq!(PrintFormat::Pivot.to_string(), "pivot");
+    }
+
+    #[test]
+    fn print_format_from_extension() {
+        for (path, format) in [
+            ("out.csv", Some(PrintFormat::Csv)),
+            ("out.TSV", Some(PrintFormat::Tsv)),
+            ("dir/out.json", Some(PrintFormat::Json)),
+            ("out.ndjson", Some(PrintFormat::NdJson)),
+            ("out.jsonl", Some(PrintFormat::NdJson)),
+            ("out.parquet", Some(PrintFormat::Parquet)),
+            ("out.arrow", Some(PrintFormat::Arrow)),
+            ("out.txt", None),
+            ("out", None),
+        ] {
+            assert_eq!(PrintFormat::from_extension(path), format, "{path}");
+        }
+
+        // the automatic format follows the extension of the output path
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Automatic)
+            .with_batches(vec![one_column_batch()])
+            .with_options(
+                PrintBatchesOptions::new().with_output_path(Some("out.jsonl".into())),
+            )
+            .run();
+        assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+    }
+
+    #[test]
+    fn print_json_with_schema() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("1"), None])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::JsonWithSchema)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(split_batch(batch))
+            .run();
+        assert_eq!(
+            output,
+            r#"{"schema":[{"name":"n","type":"Int32"},{"name":"s","type":"Utf8"}],"rows":[{"n":1,"s":"1"},{"n":2}]}"#.to_owned() + "\n"
+        );
+
+        // the schema is printed without rows too
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::JsonWithSchema)
+            .with_schema(schema)
+            .run();
+        assert_eq!(
+            output,
+            r#"{"schema":[{"name":"n","type":"Int32"},{"name":"s","type":"Utf8"}],"rows":[]}"#.to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn print_display_columns() {
+        let options = PrintBatchesOptions::new()
+            .with_display_columns(Some(vec!["c".into(), "a".into()]));
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(options.clone())
+            .run();
+        assert_snapshot!(output, @r"
+        +---+---+
+        | c | a |
+        +---+---+
+        | 7 | 1 |
+        | 8 | 2 |
+        | 9 | 3 |
+        +---+---+
+        ");
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_header(WithHeader::Yes)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(options.clone())
+            .run();
+        assert_snapshot!(output, @r"
+        c,a
+        7,1
+        8,2
+        9,3
+        ");
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Json)
+            .with_schema(three_column_schema())
+            .with_batches(vec![three_column_batch()])
+            .with_options(options)
+            .run();
+        assert_snapshot!(output, @r#"
+        [{"c":7,"a":1},{"c":8,"a":2},{"c":9,"a":3}]
+        "#);
+    }
+
+    #[test]
+    fn print_display_columns_unknown() {
+        let options = PrintBatchesOptions::new()
+            .with_display_columns(Some(vec!["a".into(), "d".into()]));
+        let mut output: Vec<u8> = vec![];
+        let err = PrintFormat::Csv
+            .print_with_options(
+                &mut output,
+                three_column_schema(),
+                &[three_column_batch()],
+                &options,
+            )
+            .unwrap_err();
+        assert_contains!(
+            err.to_string(),
+            "Unknown display column 'd', the columns are: a, b, c"
+        );
+
+        let mut state = OutputStreamState::new(
+            &mut output,
+            three_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_options(options);
+        let err = state.process_batch(&three_column_batch()).unwrap_err();
+        assert_contains!(err.to_string(), "Unknown display column 'd'");
+        drop(state);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn print_stream_display_columns() {
+        for (format, expected) in [
+            (PrintFormat::Tsv, "b\tc\n4\t7\n5\t8\n6\t9\n"),
+            (
+                PrintFormat::Table,
+                "+---+---+\n| b | c |\n+---+---+\n| 4 | 7 |\n| 5 | 8 |\n| 6 | 9 |\n+---+---+\n",
+            ),
+        ] {
+            let mut output: Vec<u8> = vec![];
+            let mut state = OutputStreamState::new(
+                &mut output,
+                three_column_schema(),
+                MaxRows::Unlimited,
+                &FormatOptions::default(),
+            )
+            .with_format(format)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_display_columns(Some(vec!["b".into(), "c".into()])),
+            );
+            for batch in split_batch(three_column_batch()) {
+                state.process_batch(&batch).unwrap();
+            }
+            state.finish().unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected, "{format}");
+        }
+    }
+
+    #[test]
+    fn print_csv_bom() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(vec![one_column_batch()])
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_csv_bom(true))
+            .run();
+        assert_eq!(output, "\u{FEFF}a\n1\n2\n3\n");
+
+        // the other formats have no mark
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_batches(vec![one_column_batch()])
+            .with_options(PrintBatchesOptions::new().with_csv_bom(true))
+            .run();
+        assert_eq!(output, "{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n");
+    }
+
+    #[test]
+    fn print_stream_csv_bom() {
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            three_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Csv)
+        .with_options(PrintBatchesOptions::new().with_csv_bom(true));
+        for batch in split_batch(three_column_batch()) {
+            state.process_batch(&batch).unwrap();
+        }
+        state.finish().unwrap();
+        // the mark precedes the first batch only
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\u{FEFF}a,b,c\n1,4,7\n2,5,8\n3,6,9\n"
+        );
+    }
+
+    #[test]
+    fn print_stream_row_count_comment() {
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            three_column_schema(),
+            MaxRows::Limited(2),
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Tsv)
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_maxrows(MaxRows::Limited(2))
+                .with_row_count_comment(true),
+        );
+        for batch in split_batch(three_column_batch()) {
+            state.process_batch(&batch).unwrap();
+        }
+        state.finish().unwrap();
+        // the comment follows the last batch only
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "a\tb\tc\n1\t4\t7\n2\t5\t8\n# 2 rows\n"
+        );
+    }
+
+    #[test]
+    fn print_tsv_no_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Tsv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::No)
+            .run();
+        assert_snapshot!(output, @"
+        1\t4\t7
+        2\t5\t8
+        3\t6\t9
+        ")
+    }
+
+    #[test]
+    fn print_tsv_with_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Tsv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .run();
+        assert_snapshot!(output, @"
+        a\tb\tc
+        1\t4\t7
+        2\t5\t8
+        3\t6\t9
+        ");
+    }
+
+    #[test]
+    fn print_table() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Ignored)
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | 3 | 6 | 9 |
+        +---+---+---+
+        "#);
+    }
+    #[test]
+    fn print_json() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Json)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Ignored)
+            .run();
+        assert_snapshot!(output, @r#"
+        [{"a":1,"b":4,"c":7},{"a":2,"b":5,"c":8},{"a":3,"b":6,"c":9}]
+        "#);
+    }
+
+    #[test]
+    fn print_json_empty_string_as_null() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["x", ""])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Json)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .run();
+        assert_snapshot!(output, @r#"
+        [{"s":"x","n":1},{"s":"","n":2}]
+        "#);
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Json)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_empty_string_as_null(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        [{"s":"x","n":1},{"s":null,"n":2}]
+        "#);
+    }
+
+    #[test]
+    fn print_json_pretty() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::JsonPretty)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Ignored)
+            .run();
+        assert_snapshot!(output, @r#"
+        [
+          {
+            "a": 1,
+            "b": 4,
+            "c": 7
+          },
+          {
+            "a": 2,
+            "b": 5,
+            "c": 8
+          },
+          {
+            "a": 3,
+            "b": 6,
+            "c": 9
+          }
+        ]
+        "#);
+    }
+
+    #[test]
+    fn print_json_pretty_strings() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Utf8, true),
+            Field::new("b", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![Some("{x: [1,2]}"), None])),
+                Arc::new(StringArray::from(vec![Some("say \"hi\", \\"), None])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::JsonPretty)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        [
+          {
+            "a": "{x: [1,2]}",
+            "b": "say \"hi\", \\"
+          },
+          {}
+        ]
+        "#);
+    }
+
+    #[test]
+    fn print_ndjson() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Ignored)
+            .run();
+        assert_snapshot!(output, @r#"
+        {"a":1,"b":4,"c":7}
+        {"a":2,"b":5,"c":8}
+        {"a":3,"b":6,"c":9}
+        "#);
+    }
+
+    #[test]
+    fn print_ndjson_trailing_newline() {
+        for trailing_newline in [true, false] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::NdJson)
+                .with_batches(split_batch(three_column_batch()))
+                .with_options(
+                    PrintBatchesOptions::new()
+                        .with_ndjson_trailing_newline(trailing_newline),
+                )
+                .run();
+            assert_eq!(output.ends_with('\n'), trailing_newline);
+            assert!(output.starts_with(r#"{"a":1,"b":4,"c":7}"#));
+            assert!(output.trim_end().ends_with(r#"{"a":3,"b":6,"c":9}"#));
+        }
+    }
+
+    #[test]
+    fn print_ndjson_with_json_schema() {
+        let mut data: Vec<u8> = vec![];
+        let mut json_schema: Vec<u8> = vec![];
+        print_ndjson_with_schema(
+            &mut data,
+            &mut json_schema,
+            three_column_schema(),
+            &split_batch(three_column_batch()),
+        )
+        .unwrap();
+
+        assert_snapshot!(String::from_utf8(data).unwrap(), @r#"
+        {"a":1,"b":4,"c":7}
+        {"a":2,"b":5,"c":8}
+        {"a":3,"b":6,"c":9}
+        "#);
+        assert_snapshot!(String::from_utf8(json_schema).unwrap(), @r#"
+        {"$schema":"http://json-schema.org/draft-07/schema#","properties":{"a":{"type":"integer"},"b":{"type":"integer"},"c":{"type":"integer"}},"required":["a","b","c"],"type":"object"}
+        "#);
+    }
+
+    #[test]
+    fn print_json_ascii_escape() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(StringArray::from(vec!["café", "😀"]))],
+        )
+        .unwrap();
+
+        for format in [PrintFormat::Json, PrintFormat::NdJson] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_batches(vec![batch.clone()])
+                .with_options(PrintBatchesOptions::new().with_json_ascii_escape(true))
+                .run();
+            assert!(output.is_ascii());
+            assert_contains!(&output, r#""s":"caf\u00e9""#);
+            assert_contains!(&output, r#""s":"\ud83d\ude00""#);
+        }
+    }
+
+    #[test]
+    fn print_json_nan_policy() {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("x", DataType::Float64, true)]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Float64Array::from(vec![
+                Some(1.5),
+                Some(f64::NAN),
+                Some(f64::INFINITY),
+                None,
+            ]))],
+        )
+        .unwrap();
+
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::Yes)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_json_nan_policy(JsonNanPolicy::NullWithWarning)
+                    .with_warnings(WarningSink::Collect(Arc::clone(&warnings))),
+            )
+            .run();
+        let values: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["x"].clone())
+            .collect();
+        assert_eq!(
+            values,
+            vec![json!(1.5), Value::Null, Value::Null, Value::Null]
+        );
+        assert_eq!(
+            *warnings.lock(),
+            vec!["2 NaN or infinite values were printed as null".to_string()]
+        );
+
+        let mut buffer: Vec<u8> = vec![];
+        let err = PrintFormat::Json
+            .print_with_options(
+                &mut buffer,
+                batch.schema(),
+                &[batch],
+                &PrintBatchesOptions::new().with_json_nan_policy(JsonNanPolicy::Error),
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "2 NaN or infinite values");
+    }
+
+    #[test]
+    fn print_automatic_no_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Automatic)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::No)
+            .run();
+        assert_snapshot!(output, @r#"
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+    }
+    #[test]
+    fn print_automatic_with_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Automatic)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .run();
+        assert_snapshot!(output, @r#"
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+    }
+
+    #[test]
+    fn print_maxrows_unlimited() {
+        // should print out entire output with no truncation if unlimited or
+        // limit greater than number of batches or equal to the number of batches
+        for max_rows in [MaxRows::Unlimited, MaxRows::Limited(5), MaxRows::Limited(3)] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_schema(one_column_schema())
+                .with_batches(vec![one_column_batch()])
+                .with_maxrows(max_rows)
+                .run();
+            allow_duplicates! {
+                assert_snapshot!(output, @r#"
+                +---+
+                | a |
+                +---+
+                | 1 |
+                | 2 |
+                | 3 |
+                +---+
+                "#);
+            }
+        }
+    }
+
+    #[test]
+    fn print_maxrows_limits_every_format() {
+        for (format, expected) in [
+            (PrintFormat::Csv, "a,b,c\n1,4,7\n2,5,8\n"),
+            (PrintFormat::Tsv, "a\tb\tc\n1\t4\t7\n2\t5\t8\n"),
+            (
+                PrintFormat::NdJson,
+                "{\"a\":1,\"b\":4,\"c\":7}\n{\"a\":2,\"b\":5,\"c\":8}\n",
+            ),
+            (
+                PrintFormat::Json,
+                "[{\"a\":1,\"b\":4,\"c\":7},{\"a\":2,\"b\":5,\"c\":8}]\n",
+            ),
+        ] {
+            // the limit spans batches
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_batches(split_batch(three_column_batch()))
+                .with_maxrows(MaxRows::Limited(2))
+                .with_header(WithHeader::Yes)
+                .run();
+            assert_eq!(output, expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn print_maxrows_short_tables() {
+        // truncation is decided from row counts rather than by cutting lines
+        // from the rendered table, so tables with a multi-line header or
+        // without a header, and limits close to the row count, print whole
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "first\nsecond",
+            DataType::Int32,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int32Array::from(vec![1, 2]))],
+        )
+        .unwrap();
+        for max_rows in [MaxRows::Limited(2), MaxRows::Limited(3)] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(vec![batch.clone()])
+                .with_maxrows(max_rows)
+                .run();
+            allow_duplicates! {
+                assert_snapshot!(output, @r#"
+                +--------+
+                |  first |
+                | second |
+                +--------+
+                |      1 |
+                |      2 |
+                +--------+
+                "#);
+            }
+
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(vec![batch.clone()])
+                .with_maxrows(max_rows)
+                .with_options(PrintBatchesOptions::new().with_table_header(false))
+                .run();
+            allow_duplicates! {
+                assert_snapshot!(output, @r#"
+                +---+
+                | 1 |
+                | 2 |
+                +---+
+                "#);
+            }
+        }
+    }
+
+    #[test]
+    fn print_maxrows_limited_one_batch() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![one_column_batch()])
+            .with_maxrows(MaxRows::Limited(1))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | . |
+        | . |
+        | . |
+        +---+
+        "#);
+    }
+
+    #[test]
+    fn print_maxrows_limited_multi_batched() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![
+                one_column_batch(),
+                one_column_batch(),
+                one_column_batch(),
+            ])
+            .with_maxrows(MaxRows::Limited(5))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | 2 |
+        | 3 |
+        | 1 |
+        | 2 |
+        | . |
+        | . |
+        | . |
+        +---+
+        "#);
+    }
+
+    #[test]
+    fn print_stream_maxrows_matches_buffered() {
+        let batches = vec![one_column_batch(), one_column_batch(), one_column_batch()];
+        for max_rows in [
+            MaxRows::Unlimited,
+            MaxRows::Limited(7),
+            MaxRows::Limited(5),
+            MaxRows::Limited(3),
+            MaxRows::Limited(1),
+            MaxRows::Limited(0),
+        ] {
+            let buffered = PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_batches(batches.clone())
+                .with_maxrows(max_rows)
+                .run();
+
+            // print rows as the batches arrive, after previewing only 2 rows
+            let mut streamed: Vec<u8> = vec![];
+            let mut state = OutputStreamState::new(
+                &mut streamed,
+                one_column_schema(),
+                max_rows,
+                &FormatOptions::default(),
+            )
+            .with_preview_limit(2);
+            for batch in &batches {
+                state.process_batch(batch).unwrap();
+            }
+            state.finish().unwrap();
+
+            assert_eq!(
+                String::from_utf8(streamed).unwrap(),
+                buffered,
+                "streamed output differs with max_rows {max_rows:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn chunked_writer_coalesces_batches() {
+        let mut writer = ChunkedWriter::new(vec![], 16, None);
+        let mut written = vec![];
+        for _ in 0..4 {
+            PrintFormat::Csv
+                .print_with_options(
+                    &mut writer,
+                    one_column_schema(),
+                    &[one_column_batch()],
+                    &PrintBatchesOptions::new().with_header(false),
+                )
+                .unwrap();
+            written.push(writer.get_ref().len());
+        }
+        // each batch is 6 bytes, so nothing is written until the third batch
+        // completes the first 16 byte chunk
+        assert_eq!(written[..2], [0, 0]);
+        assert!(written[2] >= 16);
+
+        // flushing keeps the last incomplete chunk, finishing writes it
+        writer.flush().unwrap();
+        assert!(writer.get_ref().len() < 24);
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(writer.get_ref().clone()).unwrap(),
+            "1\n2\n3\n".repeat(4)
+        );
+    }
+
+    #[test]
+    fn chunked_writer_finishes_on_drop() {
+        let mut output = vec![];
+        {
+            let mut writer = ChunkedWriter::new(&mut output, 1024, None);
+            writer.write_all(b"1\n").unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(output, b"1\n");
+    }
+
+    /// A writer failing once after accepting `fail_after` bytes
+    struct FlakyWriter {
+        written: Vec<u8>,
+        fail_after: Option<usize>,
+    }
+
+    impl Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let len = match self.fail_after {
+                Some(0) => {
+                    self.fail_after = None;
+                    return Err(std::io::ErrorKind::Other.into());
+                }
+                Some(fail_after) => {
+                    let len = buf.len().min(fail_after);
+                    self.fail_after = Some(fail_after - len);
+                    len
+                }
+                None => buf.len(),
+            };
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunked_writer_failed_write() {
+        let mut inner = FlakyWriter {
+            written: vec![],
+            fail_after: Some(3),
+        };
+        {
+            let mut writer = ChunkedWriter::new(&mut inner, 4, None);
+            assert!(writer.write_all(b"1\n2\n3\n").is_err());
+        }
+        // the drop writes the rest only, not the bytes written before failing
+        assert_eq!(inner.written, b"1\n2\n3\n");
+    }
+
+    #[test]
+    fn chunked_writer_max_delay() {
+        let mut writer = ChunkedWriter::new(vec![], 1024, Some(Duration::ZERO));
+        writer.write_all(b"1\n").unwrap();
+        assert_eq!(writer.get_ref(), b"1\n");
+    }
+
+    #[test]
+    fn print_stream_preview_bytes() {
+        let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Utf8, false)]));
+        let wide_batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec!["x".repeat(100)]))],
+        )
+        .unwrap();
+
+        // returns the number of batches processed before anything is printed
+        let batches_until_output = |policy: PreviewPolicy| {
+            let output = SharedOutput::default();
+            let mut state = OutputStreamState::new(
+                output.clone(),
+                Arc::clone(&schema),
+                MaxRows::Unlimited,
+                &FormatOptions::default(),
+            )
+            .with_preview_policy(policy);
+            for processed in 1..=10 {
+                state.process_batch(&wide_batch).unwrap();
+                if !output.contents().is_empty() {
+                    return processed;
+                }
+            }
+            usize::MAX
+        };
+
+        // three 100 byte rows reach 250 bytes before 5 rows are buffered
+        assert_eq!(batches_until_output(PreviewPolicy::Bytes(250)), 3);
+        assert_eq!(batches_until_output(PreviewPolicy::Rows(5)), 5);
+    }
+
+    #[test]
+    fn print_table_fixed_widths() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("id", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["alice", "bob"])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![batch.clone()])
+            .with_options(PrintBatchesOptions::new().with_fixed_widths(Some(vec![3, 4])))
+            .run();
+        assert_snapshot!(output, @r#"
+        +-----+------+
+        | nam |   id |
+        +-----+------+
+        | ali |    1 |
+        | bob |    2 |
+        +-----+------+
+        "#);
+
+        // one width is required per column
+        let mut buffer: Vec<u8> = vec![];
+        let err = PrintFormat::Table
+            .print_with_options(
+                &mut buffer,
+                schema,
+                &[batch],
+                &PrintBatchesOptions::new().with_fixed_widths(Some(vec![3])),
+            )
+            .unwrap_err();
+        assert_contains!(
+            err.to_string(),
+            "Expected 2 fixed column widths, one per column, but got 1"
+        );
+    }
+
+    #[test]
+    fn print_describe_columns() {
+        for format in [PrintFormat::Table, PrintFormat::Csv, PrintFormat::Json] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(three_column_schema())
+                .with_batches(split_batch(three_column_batch()))
+                .with_options(PrintBatchesOptions::new().with_describe(true))
+                .run();
+            allow_duplicates! {
+                assert_snapshot!(output, @r#"
+                +--------+-----------+-------+
+                | column | data_type | width |
+                +--------+-----------+-------+
+                | a      | Int32     |     1 |
+                | b      | Int32     |     1 |
+                | c      | Int32     |     1 |
+                +--------+-----------+-------+
+                "#);
+            }
+        }
+    }
+
+    #[test]
+    fn print_schema_without_batches() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("price", DataType::Float64, true),
+        ]));
+        let print = |format: PrintFormat, options: PrintBatchesOptions| {
+            let mut output: Vec<u8> = vec![];
+            format.print_schema(&mut output, &schema, &options).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_snapshot!(print(PrintFormat::Table, PrintBatchesOptions::new()), @r#"
+        +--------+---------+
+        | column | type    |
+        +--------+---------+
+        | id     | Int64   |
+        | name   | Utf8    |
+        | price  | Float64 |
+        +--------+---------+
+        "#);
+
+        let options = PrintBatchesOptions::new()
+            .with_border_style(BorderStyle::Unicode)
+            .with_display_columns(Some(vec!["name".into()]));
+        assert_snapshot!(print(PrintFormat::Table, options), @r#"
+        ┌────────┬──────┐
+        │ column │ type │
+        ├────────┼──────┤
+        │ name   │ Utf8 │
+        └────────┴──────┘
+        "#);
+
+        assert_snapshot!(print(PrintFormat::Csv, PrintBatchesOptions::new()), @r#"
+        column,type
+        id,Int64
+        name,Utf8
+        price,Float64
+        "#);
+    }
+
+    #[test]
+    fn print_width_histogram_stats() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("id", DataType::Int32, false),
+        ]));
+        // 20 names of 1 to 20 characters, so the 95th percentile is the
+        // 19th shortest
+        let names: Vec<String> = (1..=20).rev().map(|len| "x".repeat(len)).collect();
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(names)),
+                Arc::new(Int32Array::from_iter_values(0..20)),
+            ],
+        )
+        .unwrap();
+        for format in [PrintFormat::Table, PrintFormat::Csv] {
+            let output = PrintBatchesTest::new()
+                .with_format(format)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(split_batch(batch.clone()))
+                .with_options(PrintBatchesOptions::new().with_width_histogram(true))
+                .run();
+            allow_duplicates! {
+                assert_snapshot!(output, @r#"
+                +--------+-----+--------+-----+-----+
+                | column | min | median | p95 | max |
+                +--------+-----+--------+-----+-----+
+                | name   |   1 |     10 |  19 |  20 |
+                | id     |   1 |      1 |   2 |   2 |
+                +--------+-----+--------+-----+-----+
+                "#);
+            }
+        }
+    }
+
+    #[test]
+    fn print_describe_top_k() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "region",
+            DataType::Utf8,
+            true,
+        )]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec![
+                Some("west"),
+                Some("east"),
+                None,
+                Some("east"),
+                Some("north"),
+                Some("west"),
+                Some("east"),
+            ]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(PrintBatchesOptions::new().with_describe(true).with_top_k(2))
+            .run();
+        assert_snapshot!(output, @r#"
+        +--------+-----------+-------+--------------------+
+        | column | data_type | width | top_values         |
+        +--------+-----------+-------+--------------------+
+        | region | Utf8      |     6 | east (3), west (2) |
+        +--------+-----------+-------+--------------------+
+        "#);
+    }
+
+    #[test]
+    fn column_decisions_follow_schema_changes() {
+        let options = PrintBatchesOptions::default();
+        let mut decisions = ColumnDecisions::default();
+        let schema = one_column_schema();
+        decisions.update(&schema, &options);
+        assert_eq!(decisions.numeric, vec![true]);
+        assert_eq!(decisions.null_typed, vec![false]);
+
+        // the batches sharing the schema keep the decisions made for it
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int32Array::from(vec![1]))],
+        )
+        .unwrap();
+        decisions.update(&batch.schema(), &options);
+        assert!(Arc::ptr_eq(decisions.schema.as_ref().unwrap(), &schema));
+
+        // a batch bringing another schema is decided again
+        let evolved = Arc::new(Schema::new(vec![Field::new("a", DataType::Null, true)]));
+        decisions.update(&evolved, &options);
+        assert!(Arc::ptr_eq(decisions.schema.as_ref().unwrap(), &evolved));
+        assert_eq!(decisions.numeric, vec![false]);
+        assert_eq!(decisions.null_typed, vec![true]);
+    }
+
+    #[test]
+    fn print_table_without_header() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_table_header(false))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | 3 | 6 | 9 |
+        +---+---+---+
+        "#);
+    }
+
+    #[test]
+    fn print_table_unicode_borders() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_maxrows(MaxRows::Limited(2))
+            .with_options(
+                PrintBatchesOptions::new().with_border_style(BorderStyle::Unicode),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        ┌───┬───┬───┐
+        │ a │ b │ c │
+        ├───┼───┼───┤
+        │ 1 │ 4 │ 7 │
+        │ 2 │ 5 │ 8 │
+        │ .         │
+        │ .         │
+        │ .         │
+        └───┴───┴───┘
+        "#);
+    }
+
+    #[test]
+    fn print_table_truncation_rows() {
+        for (truncation_rows, dotted_lines) in [(1, 1), (5, 5), (0, 1)] {
+            let output = PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_batches(split_batch(three_column_batch()))
+                .with_maxrows(MaxRows::Limited(1))
+                .with_options(
+                    PrintBatchesOptions::new().with_truncation_rows(truncation_rows),
+                )
+                .run();
+            let dotted = "| .         |\n".repeat(dotted_lines);
+            // the bottom border follows the last dotted line
+            assert!(
+                output.ends_with(&format!("| 1 | 4 | 7 |\n{dotted}+---+---+---+\n")),
+                "{output}"
+            );
+        }
+    }
+
+    #[test]
+    fn print_table_header_interval() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_header_interval(Some(2)))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        | 3 | 6 | 9 |
+        +---+---+---+
+        "#);
+
+        // no header is repeated after the last row
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_header_interval(Some(3)))
+            .run();
+        assert_eq!(output.matches("| a | b | c |").count(), 1, "{output}");
+    }
+
+    #[test]
+    fn print_table_alignment_policy() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 100])),
+                Arc::new(StringArray::from(vec!["abcdef", "x"])),
+            ],
+        )
+        .unwrap();
+        let policy = AlignmentPolicy {
+            numeric_header: Alignment::Center,
+            other_header: Alignment::Center,
+            ..AlignmentPolicy::default()
+        };
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_alignment_policy(policy))
+            .run();
+        assert_snapshot!(output, @r#"
+        +-----+--------+
+        |  n  |  name  |
+        +-----+--------+
+        |   1 | abcdef |
+        | 100 | x      |
+        +-----+--------+
+        "#);
+    }
+
+    #[test]
+    fn print_table_column_alignments() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("amount", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 100])),
+                Arc::new(StringArray::from(vec!["abcdef", "x"])),
+                Arc::new(StringArray::from(vec!["1.5", "20.25"])),
+            ],
+        )
+        .unwrap();
+        let column_alignments = HashMap::from([
+            ("0".to_string(), Alignment::Left),
+            ("name".to_string(), Alignment::Center),
+            ("amount".to_string(), Alignment::Right),
+        ]);
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(
+                PrintBatchesOptions::new().with_column_alignments(column_alignments),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        +-----+--------+--------+
+        | n   |  name  | amount |
+        +-----+--------+--------+
+        | 1   | abcdef |    1.5 |
+        | 100 |   x    |  20.25 |
+        +-----+--------+--------+
+        "#);
+    }
+
+    #[test]
+    fn print_table_multi_line_cells() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("v", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["line1\nline2", "a\r\nb", "c\rd"])),
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        // the column is as wide as the widest line, and carriage returns
+        // break lines rather than being printed
+        assert_snapshot!(output, @r#"
+        +-------+---+
+        | v     | n |
+        +-------+---+
+        | line1 | 1 |
+        | line2 |   |
+        | a     | 2 |
+        | b     |   |
+        | c     | 3 |
+        | d     |   |
+        +-------+---+
+        "#);
+    }
+
+    #[test]
+    fn print_table_sort_indicators() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_sort_indicators(vec![
+                ("a".to_string(), true),
+                ("c".to_string(), false),
+            ]))
+            .run();
+        assert_snapshot!(output, @r#"
+        +-----+---+-----+
+        | a ▲ | b | c ▼ |
+        +-----+---+-----+
+        |   1 | 4 |   7 |
+        |   2 | 5 |   8 |
+        |   3 | 6 |   9 |
+        +-----+---+-----+
+        "#);
+    }
+
+    #[test]
+    fn print_table_group_headers() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("sales.q1", DataType::Int32, false),
+            Field::new("sales.q2", DataType::Int32, false),
+            Field::new("units.x", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(Int32Array::from(vec![10, 20])),
+                Arc::new(Int32Array::from(vec![30, 40])),
+                Arc::new(Int32Array::from(vec![5, 6])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(
+                PrintBatchesOptions::new().with_group_headers_delimiter(Some('.')),
+            )
+            .run();
+        // the `units` group is wider than its column, which is widened
+        assert_snapshot!(output, @r#"
+        +----+---------+-------+
+        |    | sales   | units |
+        +----+----+----+-------+
+        | id | q1 | q2 |     x |
+        +----+----+----+-------+
+        |  1 | 10 | 30 |     5 |
+        |  2 | 20 | 40 |     6 |
+        +----+----+----+-------+
+        "#);
+    }
+
+    #[test]
+    fn print_table_highlight() {
+        let highlight = RowHighlight::new(
+            |batch, row| batch.column(0).as_primitive::<Int32Type>().value(row) > 1,
+            "1;41",
+        );
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_highlight(Some(highlight)))
+            .run();
+        assert_eq!(
+            output,
+            "+---+---+---+\n\
+             | a | b | c |\n\
+             +---+---+---+\n\
+             | 1 | 4 | 7 |\n\
+             \x1b[1;41m| 2 | 5 | 8 |\x1b[0m\n\
+             \x1b[1;41m| 3 | 6 | 9 |\x1b[0m\n\
+             +---+---+---+\n"
+        );
+    }
+
+    #[test]
+    fn print_invalid_utf8() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        // SAFETY: deliberately invalid UTF-8, as may be received over FFI
+        let array = unsafe {
+            StringArray::new_unchecked(
+                OffsetBuffer::new(vec![0, 2, 5].into()),
+                Buffer::from_vec(b"oka\xffb".to_vec()),
+                None,
+            )
+        };
+        let batch =
+            RecordBatch::try_new(Arc::clone(&schema), vec![Arc::new(array)]).unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::No)
+            .run();
+        assert_eq!(output, "ok\na\u{fffd}b\n");
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(vec![batch.clone()])
+            .with_header(WithHeader::No)
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_invalid_utf8(InvalidUtf8Policy::HexEscape),
+            )
+            .run();
+        assert_eq!(output, "ok\na\\xffb\n");
+
+        let mut buffer: Vec<u8> = vec![];
+        let err = PrintFormat::Csv
+            .print_with_options(
+                &mut buffer,
+                schema,
+                &[batch],
+                &PrintBatchesOptions::new()
+                    .with_invalid_utf8(InvalidUtf8Policy::Error)
+                    .with_header(false),
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "Invalid UTF-8 in string value");
+    }
+
+    #[test]
+    fn print_csv_utf16le() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec!["café", "😀"]))],
+        )
+        .unwrap();
+
+        let mut buffer: Vec<u8> = vec![];
+        PrintFormat::Csv
+            .print_with_options(
+                &mut buffer,
+                schema,
+                &[batch],
+                &PrintBatchesOptions::new().with_encoding(OutputEncoding::Utf16Le),
+            )
+            .unwrap();
+
+        assert_eq!(&buffer[..2], &[0xFF, 0xFE]);
+        let units: Vec<u16> = buffer[2..]
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        assert_eq!(String::from_utf16(&units).unwrap(), "s\ncafé\n😀\n");
+    }
+
+    #[test]
+    fn print_table_header_range() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("s", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(40), None, Some(-3), Some(100)])),
+                Arc::new(StringArray::from(vec!["w", "x", "y", "z"])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(PrintBatchesOptions::new().with_header_range(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        +-------------+---+
+        | a [-3..100] | s |
+        +-------------+---+
+        |          40 | w |
+        |             | x |
+        |          -3 | y |
+        |         100 | z |
+        +-------------+---+
+        "#);
+    }
+
+    #[test]
+    fn print_table_header_range_with_group_digits() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, false),
+            Field::new("f", DataType::Float64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1234567, -20])),
+                Arc::new(Float64Array::from(vec![0.5, 2.25])),
+            ],
+        )
+        .unwrap();
+        // the ranges are of the values, not of their grouped digits
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_header_range(true)
+                    .with_group_digits(Some(','))
+                    .with_float_precision(Some(1)),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        +------------------+---------------+
+        | a [-20..1234567] | f [0.5..2.25] |
+        +------------------+---------------+
+        |        1,234,567 |           0.5 |
+        |              -20 |           2.2 |
+        +------------------+---------------+
+        "#);
+    }
+
+    #[test]
+    fn streamed_header_range_omitted_when_rows_follow_the_preview() {
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_options(PrintBatchesOptions::new().with_header_range(true))
+        .with_preview_limit(1);
+        for batch in split_batch(one_column_batch()) {
+            state.process_batch(&batch).unwrap();
+        }
+        state.finish().unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap(), @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | 2 |
+        | 3 |
+        +---+
+        "#);
+    }
+
+    #[test]
+    fn print_fixed_width_column_gap() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("x", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 200])),
+                Arc::new(StringArray::from(vec!["alice", "bo"])),
+                Arc::new(Int32Array::from(vec![7, 8])),
+            ],
+        )
+        .unwrap();
+        let output = |column_gap: usize| {
+            PrintBatchesTest::new()
+                .with_format(PrintFormat::FixedWidth)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(split_batch(batch.clone()))
+                .with_header(WithHeader::Yes)
+                .with_options(PrintBatchesOptions::new().with_column_gap(column_gap))
+                .run()
+        };
+
+        assert_snapshot!(output(0), @r#"
+        id name x
+        1  alice7
+        200bo   8
+        "#);
+        assert_snapshot!(output(1), @r#"
+        id  name  x
+        1   alice 7
+        200 bo    8
+        "#);
+        assert_snapshot!(output(3), @r#"
+        id    name    x
+        1     alice   7
+        200   bo      8
+        "#);
+    }
+
+    #[test]
+    fn print_fixed_width_pad_right() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("s", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["x", "long"])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::FixedWidth)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_pad_right(true))
+            .run();
+        assert_eq!(output, "a s   \n1 x   \n2 long\n");
+    }
+
+    #[test]
+    fn print_base64_wrap() {
+        for line_width in [None, Some(8)] {
+            let test = || {
+                PrintBatchesTest::new()
+                    .with_format(PrintFormat::Table)
+                    .with_batches(split_batch(three_column_batch()))
+            };
+            let output = test().run();
+            let wrapped = test()
+                .with_options(
+                    PrintBatchesOptions::new()
+                        .with_base64_wrap(true)
+                        .with_base64_line_width(line_width),
+                )
+                .run();
+
+            let mut lines = wrapped.lines();
+            assert_eq!(lines.next(), Some("base64:table"));
+            let lines: Vec<&str> = lines.collect();
+            if let Some(line_width) = line_width {
+                assert!(lines.iter().all(|line| line.len() <= line_width));
+            } else {
+                assert_eq!(lines.len(), 1);
+            }
+            let decoded = STANDARD.decode(lines.concat()).unwrap();
+            assert_eq!(String::from_utf8(decoded).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn print_table_width_stable_after() {
+        let batch = RecordBatch::try_new(
+            one_column_schema(),
+            vec![Arc::new(Int32Array::from(vec![1, 22, 3, 4, 55555]))],
+        )
+        .unwrap();
+        // the widths are final after "3" and "4", so "55555" is not scanned
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_width_stable_after(Some(2)))
+            .run();
+        assert_snapshot!(output, @r#"
+        +----+
+        |  a |
+        +----+
+        |  1 |
+        | 22 |
+        |  3 |
+        |  4 |
+        | 55 |
+        +----+
+        "#);
+    }
+
+    #[test]
+    fn print_section_label() {
+        let options =
+            PrintBatchesOptions::new().with_section_label(Some("result 1".to_string()));
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .with_options(options.clone())
+            .run();
+        assert_snapshot!(output, @r#"
+        # === result 1 ===
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        # === end result 1 ===
+        "#);
+
+        // JSON has no comments
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(options)
+            .run();
+        assert_snapshot!(output, @r#"
+        === result 1 ===
+        {"a":1,"b":4,"c":7}
+        {"a":2,"b":5,"c":8}
+        {"a":3,"b":6,"c":9}
+        === end result 1 ===
+        "#);
+    }
+
+    #[test]
+    fn print_table_wrap_headers() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("long_column_name", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![12345678, 2])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(PrintBatchesOptions::new().with_wrap_headers(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        +----------+---+
+        |    long_ | b |
+        |  column_ |   |
+        |     name |   |
+        +----------+---+
+        | 12345678 | 1 |
+        |        2 | 2 |
+        +----------+---+
+        "#);
+    }
+
+    #[test]
+    fn print_csv_sanitize_formulas() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["=SUM(A1)", "@x", "plain", "-1"])),
+                Arc::new(Int32Array::from(vec![-1, 2, 3, 4])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_sanitize_formulas(true))
+            .run();
+        // numbers are not string values, so they are not changed
+        assert_snapshot!(output, @r#"
+        s,n
+        '=SUM(A1),-1
+        '@x,2
+        plain,3
+        '-1,4
+        "#);
+    }
+
+    #[test]
+    fn print_table_type_renderer() {
+        let data_type = DataType::Timestamp(TimeUnit::Nanosecond, None);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("ts", data_type.clone(), true),
+            Field::new("n", DataType::Int64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(TimestampNanosecondArray::from(vec![
+                    Some(1_000_000_000),
+                    None,
+                    Some(61_000_000_000),
+                ])),
+                Arc::new(Int64Array::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+        let options =
+            PrintBatchesOptions::new().with_type_renderer(data_type, |array, row| {
+                let nanos = array.as_primitive::<TimestampNanosecondType>().value(row);
+                format!("{} seconds", nanos / 1_000_000_000)
+            });
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(options)
+            .run();
+        assert_snapshot!(output, @r#"
+        +------------+---+
+        | ts         | n |
+        +------------+---+
+        | 1 seconds  | 1 |
+        |            | 2 |
+        | 61 seconds | 3 |
+        +------------+---+
+        "#);
+    }
+
+    #[test]
+    fn collect_batches_for_reuse() {
+        let batches = split_batch(three_column_batch());
+        let collected = collect_for_reuse(three_column_schema(), &batches).unwrap();
+        assert_eq!(collected, vec![three_column_batch()]);
+
+        // no batches is a single empty batch of the schema
+        let collected = collect_for_reuse(three_column_schema(), &[]).unwrap();
+        assert_eq!(collected.len(), 1);
+        assert_eq!(collected[0].num_rows(), 0);
+        assert_eq!(collected[0].schema(), three_column_schema());
+
+        // batches of another schema are rejected
+        let err =
+            collect_for_reuse(three_column_schema(), &[one_column_batch()]).unwrap_err();
+        assert_contains!(err.to_string(), "Expected batches with the schema");
+    }
+
+    #[test]
+    fn print_multi_targets() {
+        let mut table: Vec<u8> = vec![];
+        let mut csv: Vec<u8> = vec![];
+        let mut json: Vec<u8> = vec![];
+        let targets = vec![
+            PrintTarget::new(&mut table, PrintFormat::Table, MaxRows::Limited(2)),
+            PrintTarget::new(&mut csv, PrintFormat::Csv, MaxRows::Unlimited),
+            PrintTarget::new(&mut json, PrintFormat::Json, MaxRows::Limited(1)),
+        ];
+        print_multi(
+            targets,
+            three_column_schema(),
+            &split_batch(three_column_batch()),
+            &FormatOptions::default(),
+            &PrintBatchesOptions::default(),
+        )
+        .unwrap();
+
+        assert_snapshot!(String::from_utf8(table).unwrap(), @r#"
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | .         |
+        | .         |
+        | .         |
+        +---+---+---+
+        "#);
+        assert_snapshot!(String::from_utf8(csv).unwrap(), @r#"
+        a,b,c
+        1,4,7
+        2,5,8
+        3,6,9
+        "#);
+        assert_snapshot!(String::from_utf8(json).unwrap(), @r#"
+        [{"a":1,"b":4,"c":7}]
+        "#);
+
+        // the header of the options applies to every target
+        let mut csv: Vec<u8> = vec![];
+        print_multi(
+            vec![PrintTarget::new(
+                &mut csv,
+                PrintFormat::Csv,
+                MaxRows::Limited(1),
+            )],
+            three_column_schema(),
+            &split_batch(three_column_batch()),
+            &FormatOptions::default(),
+            &PrintBatchesOptions::new().with_header(false),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "1,4,7\n");
+    }
+
+    #[test]
+    fn print_dual_table_and_ndjson() {
+        let batches = split_batch(three_column_batch());
+        let mut human: Vec<u8> = vec![];
+        let mut machine: Vec<u8> = vec![];
+        print_dual(
+            &mut human,
+            &mut machine,
+            three_column_schema(),
+            &batches,
+            &PrintBatchesOptions::default(),
+        )
+        .unwrap();
+
+        // each sink receives what printing the format alone would
+        let table = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(batches.clone())
+            .run();
+        let ndjson = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_batches(batches)
+            .run();
+        assert_eq!(String::from_utf8(human).unwrap(), table);
+        assert_eq!(String::from_utf8(machine).unwrap(), ndjson);
+        assert_snapshot!(ndjson, @r#"
+        {"a":1,"b":4,"c":7}
+        {"a":2,"b":5,"c":8}
+        {"a":3,"b":6,"c":9}
+        "#);
+
+        // the JSON options apply to the NdJson sink too
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, false),
+            Field::new("x", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["café", ""])),
+                Arc::new(Float64Array::from(vec![Some(f64::NAN), None])),
+            ],
+        )
+        .unwrap();
+        let options = PrintBatchesOptions::new()
+            .with_ndjson_trailing_newline(false)
+            .with_json_ascii_escape(true)
+            .with_json_nan_policy(JsonNanPolicy::Null)
+            .with_empty_string_as_null(true);
+        let mut human: Vec<u8> = vec![];
+        let mut machine: Vec<u8> = vec![];
+        print_dual(
+            &mut human,
+            &mut machine,
+            Arc::clone(&schema),
+            &split_batch(batch.clone()),
+            &options,
+        )
+        .unwrap();
+        let ndjson = PrintBatchesTest::new()
+            .with_format(PrintFormat::NdJson)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(options)
+            .run();
+        assert_eq!(String::from_utf8(machine).unwrap(), ndjson);
+        assert_contains!(&ndjson, r#""s":"caf\u00e9""#);
+        assert!(!ndjson.ends_with('\n'));
+    }
+
+    #[test]
+    fn print_null_type_column() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Null, true),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(NullArray::new(2)),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+        let format_options = FormatOptions {
+            null: "NULL".to_string(),
+            ..FormatOptions::default()
+        };
+
+        let print = |format: PrintFormat| {
+            let mut buffer: Vec<u8> = vec![];
+            format
+                .print_with_options(
+                    &mut buffer,
+                    Arc::clone(&schema),
+                    &[batch.clone()],
+                    &PrintBatchesOptions::new()
+                        .with_format_options(format_options.clone()),
+                )
+                .unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+        assert_snapshot!(print(PrintFormat::Table), @r#"
+        +------+---+
+        | n    | b |
+        +------+---+
+        | NULL | 1 |
+        | NULL | 2 |
+        +------+---+
+        "#);
+        assert_snapshot!(print(PrintFormat::Csv), @r#"
+        n,b
+        NULL,1
+        NULL,2
+        "#);
+    }
+
+    #[test]
+    fn print_table_wide_characters() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("s", DataType::Utf8, false),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["日本語", "abc", "😀"])),
+                Arc::new(Int32Array::from(vec![1, 22, 3])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        // full-width characters take two columns, so every line is as wide
+        // as the borders
+        assert!(output.lines().all(|line| line.width() == 15));
+        assert_snapshot!(output, @r#"
+        +--------+----+
+        | s      |  n |
+        +--------+----+
+        | 日本語 |  1 |
+        | abc    | 22 |
+        | 😀     |  3 |
+        +--------+----+
+        "#);
+    }
+
+    #[test]
+    fn print_table_ansi_escapes() {
+        assert_eq!(line_width("\x1b[31mred\x1b[0m"), 3);
+        assert_eq!(line_width("\x1b[1;38;5;208mbold\x1b[0m!"), 5);
+
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "colour",
+            DataType::Utf8,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec![
+                "\x1b[31mred\x1b[0m",
+                "blue",
+            ]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        // the escapes are written unchanged but do not widen the column
+        assert_eq!(
+            output,
+            "+--------+\n\
+             | colour |\n\
+             +--------+\n\
+             | \x1b[31mred\x1b[0m    |\n\
+             | blue   |\n\
+             +--------+\n"
+        );
+    }
+
+    #[test]
+    fn print_table_max_column_width() {
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec![
+                "short",
+                "héllo wörld",
+                "日本語テキスト",
+            ]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_max_column_width(Some(6)))
+            .run();
+        // a wide character that does not fit before the `…` is dropped whole
+        assert_snapshot!(output, @r#"
+        +--------+
+        | s      |
+        +--------+
+        | short  |
+        | héllo… |
+        | 日本…  |
+        +--------+
+        "#);
+    }
+
+    #[test]
+    fn print_table_min_column_width() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("", DataType::Utf8, true),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![None::<&str>, None])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+        let output = |options: PrintBatchesOptions| {
+            PrintBatchesTest::new()
+                .with_format(PrintFormat::Table)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(split_batch(batch.clone()))
+                .with_options(options)
+                .run()
+        };
+
+        assert_snapshot!(output(PrintBatchesOptions::new()), @r#"
+        +---+---+
+        |   | b |
+        +---+---+
+        |   | 1 |
+        |   | 2 |
+        +---+---+
+        "#);
+        assert_snapshot!(output(PrintBatchesOptions::new().with_min_column_width(3)), @r#"
+        +-----+-----+
+        |     |   b |
+        +-----+-----+
+        |     |   1 |
+        |     |   2 |
+        +-----+-----+
+        "#);
+    }
+
+    #[test]
+    fn print_csv_batch_index_column() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(split_batch(three_column_batch()))
+            .with_header(WithHeader::Yes)
+            .with_options(PrintBatchesOptions::new().with_batch_index_column(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        batch_index,a,b,c
+        0,1,4,7
+        1,2,5,8
+        1,3,6,9
+        "#);
+    }
+
+    #[test]
+    fn print_markdown() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Markdown)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .run();
+        assert_snapshot!(output, @r#"
+        | a | b | c |
+        |---|---|---|
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | 3 | 6 | 9 |
+        "#);
+    }
+
+    #[test]
+    fn print_markdown_escapes_pipes() {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("a|b", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec![
+                "x|y",
+                "two\nlines",
+                "dos\r\nlines",
+                "mac\rlines",
+            ]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Markdown)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        | a\|b         |
+        |--------------|
+        | x\|y         |
+        | two<br>lines |
+        | dos<br>lines |
+        | mac<br>lines |
+        "#);
+    }
+
+    #[test]
+    fn print_markdown_ignores_border_style() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Markdown)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(
+                PrintBatchesOptions::new().with_border_style(BorderStyle::Unicode),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        | a | b | c |
+        |---|---|---|
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | 3 | 6 | 9 |
+        "#);
+    }
+
+    #[test]
+    fn print_html() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Html)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_html_table_class("results"))
+            .run();
+        assert_snapshot!(output, @r#"
+        <table class="results">
+        <thead>
+        <tr><th>a</th><th>b</th><th>c</th></tr>
+        </thead>
+        <tbody>
+        <tr><td>1</td><td>4</td><td>7</td></tr>
+        <tr><td>2</td><td>5</td><td>8</td></tr>
+        <tr><td>3</td><td>6</td><td>9</td></tr>
+        </tbody>
+        </table>
+        "#);
+    }
+
+    #[test]
+    fn print_html_css_classes() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int32, true),
+            Field::new("s", DataType::Utf8, true),
+            Field::new("b", DataType::Boolean, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1), None])),
+                Arc::new(StringArray::from(vec![None, Some("x")])),
+                Arc::new(BooleanArray::from(vec![true, false])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Html)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(PrintBatchesOptions::new().with_html_css_classes(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        <table>
+        <thead>
+        <tr><th>n</th><th>s</th><th>b</th></tr>
+        </thead>
+        <tbody>
+        <tr><td class="num">1</td><td class="null"></td><td>true</td></tr>
+        <tr><td class="null"></td><td class="str">x</td><td>false</td></tr>
+        </tbody>
+        </table>
+        "#);
+    }
+
+    #[test]
+    fn print_html_escapes_cells() {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("<a>", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec!["x & \"y\"", "<b>'z'</b>"]))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Html)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        <table>
+        <thead>
+        <tr><th>&lt;a&gt;</th></tr>
+        </thead>
+        <tbody>
+        <tr><td>x &amp; &quot;y&quot;</td></tr>
+        <tr><td>&lt;b&gt;&#39;z&#39;&lt;/b&gt;</td></tr>
+        </tbody>
+        </table>
+        "#);
+    }
+
+    #[test]
+    fn print_html_empty() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Html)
+            .with_schema(one_column_schema())
+            .with_batches(vec![])
+            .run();
+        assert_snapshot!(output, @r#"
+        <table>
+        <thead>
+        <tr><th>a</th></tr>
+        </thead>
+        <tbody>
+        </tbody>
+        </table>
+        "#);
+    }
+
+    #[test]
+    fn print_logfmt() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Logfmt)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .run();
+        assert_snapshot!(output, @r#"
+        a=1 b=4 c=7
+        a=2 b=5 c=8
+        a=3 b=6 c=9
+        "#);
+    }
+
+    #[test]
+    fn print_logfmt_level_and_quoting() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("user name", DataType::Utf8, true),
+            Field::new("msg", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![Some("alice"), None])),
+                Arc::new(StringArray::from(vec![
+                    Some("said \"a=b\""),
+                    Some("two\nlines"),
+                ])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Logfmt)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_logfmt_level("info"))
+            .run();
+        assert_snapshot!(output, @r#"
+        level=info user_name=alice msg="said \"a=b\""
+        level=info user_name= msg="two\nlines"
+        "#);
+    }
+
+    #[test]
+    fn print_latex() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Latex)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .run();
+        assert_snapshot!(output, @r#"
+        \begin{tabular}{rrr}
+        \toprule
+        a & b & c \\
+        \midrule
+        1 & 4 & 7 \\
+        2 & 5 & 8 \\
+        3 & 6 & 9 \\
+        \bottomrule
+        \end{tabular}
+        "#);
+    }
+
+    #[test]
+    fn print_latex_escapes_cells() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("item_name", DataType::Utf8, false),
+            Field::new("price", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["50% off & #1 {$}"])),
+                Arc::new(Int32Array::from(vec![10])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Latex)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        \begin{tabular}{lr}
+        \toprule
+        item\_name & price \\
+        \midrule
+        50\% off \& \#1 \{\$\} & 10 \\
+        \bottomrule
+        \end{tabular}
+        "#);
+    }
+
+    #[test]
+    fn print_table_column_priorities() {
+        let priorities = HashMap::from([("a".to_string(), 2), ("b".to_string(), -1)]);
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![three_column_batch()])
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_max_table_width(Some(10))
+                    .with_column_priorities(priorities),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+
+        | a | c |
+        +---+---+
+        | 1 | 7 |
+        | 2 | 8 |
+        | 3 | 9 |
+        +---+---+
+        Dropped columns: b
+        "#);
+    }
+
+    #[test]
+    fn print_table_sticky_first_column() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_max_table_width(Some(10))
+                    .with_sticky_first_column(true),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+
+        | a | b |
+        +---+---+
+        | 1 | 4 |
+        | 2 | 5 |
+        | 3 | 6 |
+        +---+---+
+        +---+---+
+        | a | c |
+        +---+---+
+        | 1 | 7 |
+        | 2 | 8 |
+        | 3 | 9 |
+        +---+---+
+        "#);
+    }
+
+    #[test]
+    fn print_table_header_from_metadata() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("amt", DataType::Int32, false).with_metadata(HashMap::from([(
+                "display_name".to_string(),
+                "Total amount".to_string(),
+            )])),
+            Field::new("b", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(Int32Array::from(vec![3, 4])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_header_from_metadata(Some("display_name".to_string())),
+            )
+            .run();
+        assert_snapshot!(output, @r#"
+        +--------------+---+
+        | Total amount | b |
+        +--------------+---+
+        |            1 | 3 |
+        |            2 | 4 |
+        +--------------+---+
+        "#);
+    }
+
+    #[test]
+    fn print_vertical() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Vertical)
+            .with_schema(three_column_schema())
+            .with_batches(vec![three_column_batch().slice(0, 2)])
+            .run();
+        assert_snapshot!(output, @r#"
+        -[ RECORD 1 ]-
+        a | 1
+        b | 4
+        c | 7
+        -[ RECORD 2 ]-
+        a | 2
+        b | 5
+        c | 8
+        "#);
+    }
+
+    #[test]
+    fn print_vertical_pads_names() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("comment", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["first\nsecond"])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Vertical)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        -[ RECORD 1 ]-
+        id      | 1
+        comment | first
+                | second
+        "#);
+    }
+
+    #[test]
+    fn print_ini() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Ini)
+            .with_schema(three_column_schema())
+            .with_batches(split_batch(three_column_batch()))
+            .run();
+        assert_snapshot!(output, @r#"
+        [row 1]
+        a=1
+        b=4
+        c=7
+
+        [row 2]
+        a=2
+        b=5
+        c=8
+
+        [row 3]
+        a=3
+        b=6
+        c=9
+        "#);
+    }
+
+    #[test]
+    fn print_ini_nulls() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int32, true),
+            Field::new("b", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(1)])),
+                Arc::new(Int32Array::from(vec![None])),
+            ],
+        )
+        .unwrap();
+        let test = |omit_nulls: bool| {
+            PrintBatchesTest::new()
+                .with_format(PrintFormat::Ini)
+                .with_schema(Arc::clone(&schema))
+                .with_batches(vec![batch.clone()])
+                .with_options(PrintBatchesOptions::new().with_ini_omit_nulls(omit_nulls))
+                .run()
+        };
+        assert_eq!(test(false), "[row 1]\na=1\nb=\n");
+        assert_eq!(test(true), "[row 1]\na=1\n");
+    }
+
+    #[test]
+    fn print_ini_quotes_special_characters() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("a=b", DataType::Utf8, false),
+            Field::new("[c]", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["two\n[lines]", "x"])),
+                Arc::new(StringArray::from(vec![" padded ", "say \"hi\"; ok"])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Ini)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .run();
+        assert_snapshot!(output, @r#"
+        [row 1]
+        "a=b"="two\n[lines]"
+        "[c]"=" padded "
+
+        [row 2]
+        "a=b"=x
+        "[c]"="say \"hi\"; ok"
+        "#);
+    }
+
+    #[test]
+    fn print_table_collapsed_truncation() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "measured_value_in_units",
+            DataType::Int32,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(Int32Array::from_iter_values(0..1200))],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_maxrows(MaxRows::Limited(2))
+            .with_options(PrintBatchesOptions::new().with_collapsed_truncation(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        +-------------------------+
+        | measured_value_in_units |
+        +-------------------------+
+        |                       0 |
+        |                       1 |
+        | … and 1,198 more rows   |
+        +-------------------------+
+        "#);
+    }
+
+    #[test]
+    fn print_bool_tokens() {
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "flag",
+            DataType::Boolean,
+            true,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(BooleanArray::from(vec![
+                Some(true),
+                Some(false),
+                None,
+            ]))],
+        )
+        .unwrap();
+        let options = PrintBatchesOptions::new().with_bool_tokens(Some((
+            "yes".to_string(),
+            "no".to_string(),
+            "?".to_string(),
+        )));
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![batch.clone()])
+            .with_options(options.clone())
+            .run();
+        assert_snapshot!(output, @r#"
+        +------+
+        | flag |
+        +------+
+        | yes  |
+        | no   |
+        | ?    |
+        +------+
+        "#);
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Csv)
+            .with_batches(vec![batch])
+            .with_header(WithHeader::Yes)
+            .with_options(options)
+            .run();
+        assert_snapshot!(output, @r#"
+        flag
+        yes
+        no
+        ?
+        "#);
+    }
+
+    #[test]
+    fn print_table_colorize() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("n", DataType::Int32, true),
+            Field::new("s", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![Some(10), None])),
+                Arc::new(StringArray::from(vec![Some("x"), Some("y")])),
+            ],
+        )
+        .unwrap();
+        let format_options = FormatOptions {
+            null: "NULL".to_string(),
+            ..Default::default()
+        };
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            schema,
+            MaxRows::Unlimited,
+            &format_options,
+        )
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_format_options(format_options.clone())
+                .with_colorize(true)
+                .with_numeric_color(Some("36".to_string())),
+        );
+        state.process_batch(&batch).unwrap();
+        state.finish().unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "+------+---+",
+                "|    \x1b[1mn\x1b[0m | \x1b[1ms\x1b[0m |",
+                "+------+---+",
+                "|   \x1b[36m10\x1b[0m | x |",
+                "| \x1b[2mNULL\x1b[0m | y |",
+                "+------+---+",
+            ]
+        );
+        // the codes do not affect the widths, so the borders line up
+        assert!(lines.iter().all(|line| line_width(line) == lines[0].len()));
+    }
+
+    #[test]
+    fn print_table_categorical_colors() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("fruit", DataType::Utf8, true),
+            Field::new("n", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("apple"),
+                    Some("pear"),
+                    Some("apple"),
+                    None,
+                ])),
+                Arc::new(Int32Array::from(vec![1, 2, 3, 4])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(split_batch(batch))
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_categorical_color_columns(HashSet::from(["fruit".to_string()])),
+            )
+            .run();
+        let lines: Vec<&str> = output.lines().collect();
+        let color = |line: &str| {
+            let start = line.find("\x1b[").unwrap();
+            let end = start + line[start..].find('m').unwrap();
+            line[start..=end].to_string()
+        };
+        // equal values get equal colors in every batch
+        assert_eq!(color(lines[3]), color(lines[5]));
+        assert!(lines[3].contains("apple\x1b[0m"));
+        assert!(lines[4].contains("pear\x1b[0m"));
+        // nulls and other columns are not colored
+        assert!(!lines[6].contains('\x1b'));
+        assert!(lines[3].ends_with("apple\x1b[0m | 1 |"));
+        // the colors do not affect the widths
+        assert!(lines.iter().all(|line| line_width(line) == lines[0].len()));
+
+        let palette = PrintBatchesOptions::new().categorical_palette;
+        assert_eq!(
+            categorical_color("apple", &palette),
+            categorical_color("apple", &palette)
+        );
+        assert_eq!(categorical_color("apple", &["1".to_string()]), Some("1"));
+        assert_eq!(categorical_color("apple", &[]), None);
+    }
+
+    #[test]
+    fn print_table_fit_width() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("description", DataType::Utf8, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["alice", "bob"])),
+                Arc::new(StringArray::from(vec![
+                    "a rather long description",
+                    "short",
+                ])),
+            ],
+        )
+        .unwrap();
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_schema(schema)
+            .with_batches(vec![batch])
+            .with_options(PrintBatchesOptions::new().with_fit_width(Some(20)))
+            .run();
+        // the description is narrowed until it is as wide as the name, then
+        // both are narrowed together, and the id is left intact
+        assert_snapshot!(output, @r#"
+        +----+------+------+
+        | id | name | des… |
+        +----+------+------+
+        |  1 | ali… | a r… |
+        |  2 | bob  | sho… |
+        +----+------+------+
+        "#);
+        assert!(output.lines().all(|line| line.width() == 20));
+
+        let mut widths = vec![2, 3, 30];
+        shrink_to_fit(&mut widths, 20);
+        assert_eq!(widths, vec![2, 3, 5]);
+        let mut widths = vec![2, 3, 30];
+        shrink_to_fit(&mut widths, 5);
+        assert_eq!(widths, vec![1, 1, 1]);
+        // the width left over goes to the leftmost of the narrowed columns
+        let mut widths = vec![9, 2, 9, 9];
+        shrink_to_fit(&mut widths, 32);
+        assert_eq!(widths, vec![6, 2, 6, 5]);
+    }
+
+    #[test]
+    fn print_table_row_numbers() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_row_numbers(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+---+
+        | # | a | b | c |
+        +---+---+---+---+
+        | 1 | 1 | 4 | 7 |
+        | 2 | 2 | 5 | 8 |
+        | 3 | 3 | 6 | 9 |
+        +---+---+---+---+
+        "#);
+    }
+
+    #[test]
+    fn print_table_row_count_footer() {
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(split_batch(three_column_batch()))
+            .with_options(PrintBatchesOptions::new().with_row_count_footer(true))
+            .run();
+        assert_snapshot!(output, @r#"
+        +---+---+---+
+        | a | b | c |
+        +---+---+---+
+        | 1 | 4 | 7 |
+        | 2 | 5 | 8 |
+        | 3 | 6 | 9 |
+        +---+---+---+
+        (3 rows)
+        "#);
+
+        // the count accumulates across streamed batches, including the
+        // rows omitted because of `max_rows`
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            one_column_schema(),
+            MaxRows::Limited(2),
+            &FormatOptions::default(),
+        )
+        .with_preview_limit(0)
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_maxrows(MaxRows::Limited(2))
+                .with_row_count_footer(true),
+        );
+        for _ in 0..2 {
+            state.process_batch(&one_column_batch()).unwrap();
+        }
+        state.finish().unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap(), @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | 2 |
+        | . |
+        | . |
+        | . |
+        +---+
+        (showing 2 of 6 rows)
+        "#);
+    }
+
+    /// A writer accepting `capacity` bytes, then failing with a broken pipe
+    /// as if the reader exited
+    struct ClosingWriter {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl ClosingWriter {
+        fn new(capacity: usize) -> Self {
+            Self {
+                written: vec![],
+                capacity,
+            }
+        }
+    }
+
+    impl Write for ClosingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(self.capacity - self.written.len());
+            if len == 0 {
+                return Err(std::io::ErrorKind::BrokenPipe.into());
+            }
+            self.written.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A writer whose output can be read while an [`OutputStreamState`] owns
+    /// a clone of it
+    #[derive(Debug, Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedOutput {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn print_batches_broken_pipe() {
+        for format in [PrintFormat::Csv, PrintFormat::Table, PrintFormat::Json] {
+            let mut writer = ClosingWriter::new(8);
+            format
+                .print_with_options(
+                    &mut writer,
+                    three_column_schema(),
+                    &split_batch(three_column_batch()),
+                    &PrintBatchesOptions::new(),
+                )
+                .unwrap();
+            assert_eq!(writer.written.len(), 8);
+
+            let mut writer = ClosingWriter::new(8);
+            let result = format.print_with_options(
+                &mut writer,
+                three_column_schema(),
+                &split_batch(three_column_batch()),
+                &PrintBatchesOptions::new().with_ignore_broken_pipe(false),
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn print_stream_broken_pipe() {
+        let mut writer = ClosingWriter::new(20);
+        let mut state = OutputStreamState::new(
+            &mut writer,
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_preview_limit(0);
+        // the writer closes while the first batch is printed, and later
+        // batches are not written
+        for _ in 0..3 {
+            state.process_batch(&one_column_batch()).unwrap();
+        }
+        state.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(writer.written).unwrap(),
+            "+---+\n| a |\n+---+\n| "
+        );
+    }
+
+    #[test]
+    fn print_stream_row_numbers() {
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            one_column_schema(),
+            MaxRows::Limited(10),
+            &FormatOptions::default(),
+        )
+        .with_preview_limit(0)
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_maxrows(MaxRows::Limited(10))
+                .with_row_numbers(true),
+        );
+        for _ in 0..4 {
+            state.process_batch(&one_column_batch()).unwrap();
+        }
+        state.finish().unwrap();
+        // the number column is sized for the last row that may be printed
+        assert_snapshot!(String::from_utf8(output).unwrap(), @r#"
+        +----+---+
+        |  # | a |
+        +----+---+
+        |  1 | 1 |
+        |  2 | 2 |
+        |  3 | 3 |
+        |  4 | 1 |
+        |  5 | 2 |
+        |  6 | 3 |
+        |  7 | 1 |
+        |  8 | 2 |
+        |  9 | 3 |
+        | 10 | 1 |
+        | .      |
+        | .      |
+        | .      |
+        +----+---+
+        "#);
+    }
+
+    #[test]
+    fn print_stream_row_numbers_unlimited() {
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_preview_limit(0)
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_maxrows(MaxRows::Unlimited)
+                .with_row_numbers(true),
+        );
+        for _ in 0..4 {
+            state.process_batch(&one_column_batch()).unwrap();
+        }
+        state.finish().unwrap();
+        // the number column is sized for six digits, as the number of rows
+        // is unknown when the first batch is printed
+        let output = String::from_utf8(output).unwrap();
+        assert_contains!(&output, "|      1 | 1 |");
+        assert_contains!(&output, "|     12 | 3 |");
+        let width = output.lines().next().unwrap().len();
+        assert!(output.lines().all(|line| line.len() == width));
+    }
+
+    #[test]
+    fn print_table_legend() {
+        let schema =
+            Arc::new(Schema::new(vec![Field::new("name", DataType::Utf8, true)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec![Some("apple"), None]))],
+        )
+        .unwrap();
+        let format_options = FormatOptions {
+            null: "NULL".to_string(),
+            ..Default::default()
+        };
+        let options = PrintBatchesOptions::new()
+            .with_max_column_width(Some(4))
+            .with_print_legend(true);
+        let mut output: Vec<u8> = vec![];
+        PrintFormat::Table
+            .print_with_options(
+                &mut output,
+                schema,
+                &[batch],
+                &options.clone().with_format_options(format_options.clone()),
+            )
+            .unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap(), @r#"
+        +------+
+        | name |
+        +------+
+        | app… |
+        | NULL |
+        +------+
+        NULL = null, … = truncated
+        "#);
+
+        // no legend when no marker was used
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![three_column_batch()])
+            .with_options(options)
+            .run();
+        assert!(output.ends_with("+---+---+---+\n"), "{output}");
+    }
+
+    #[test]
+    fn print_stream_dedup_consecutive_batches() {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_dedup_consecutive_batches(true)
+                .with_warnings(WarningSink::Collect(Arc::clone(&warnings))),
+        );
+        let other_batch = RecordBatch::try_new(
+            one_column_schema(),
+            vec![Arc::new(Int32Array::from(vec![100]))],
+        )
+        .unwrap();
+        // only the repeat immediately after the first batch is skipped
+        for batch in [
+            one_column_batch(),
+            one_column_batch(),
+            other_batch,
+            one_column_batch(),
+        ] {
+            state.process_batch(&batch).unwrap();
+        }
+        state.finish().unwrap();
+        assert_snapshot!(String::from_utf8(output).unwrap(), @r#"
+        +-----+
+        |   a |
+        +-----+
+        |   1 |
+        |   2 |
+        |   3 |
+        | 100 |
+        |   1 |
+        |   2 |
+        |   3 |
+        +-----+
+        "#);
+        assert_eq!(
+            *warnings.lock(),
+            vec!["skipped a batch of 3 rows identical to the previous batch".to_string()]
+        );
+    }
+
+    #[test]
+    fn print_stream_without_preview() {
+        let output = SharedOutput::default();
+        let mut state = OutputStreamState::new(
+            output.clone(),
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_preview_limit(0);
+
+        // an empty batch can not size the columns
+        let empty_batch = RecordBatch::new_empty(one_column_schema());
+        state.process_batch(&empty_batch).unwrap();
+        assert!(output.contents().is_empty());
+
+        // the first batch is printed before the next one arrives
+        state.process_batch(&one_column_batch()).unwrap();
+        assert_snapshot!(output.contents(), @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | 2 |
+        | 3 |
+        "#);
+
+        // wider values in later batches overflow their column
+        let wide_batch = RecordBatch::try_new(
+            one_column_schema(),
+            vec![Arc::new(Int32Array::from(vec![100]))],
+        )
+        .unwrap();
+        state.process_batch(&wide_batch).unwrap();
+        state.finish().unwrap();
+        assert_snapshot!(output.contents(), @r#"
+        +---+
+        | a |
+        +---+
+        | 1 |
+        | 2 |
+        | 3 |
+        | 100 |
+        +---+
+        "#);
+    }
+
+    #[test]
+    fn print_stream_csv() {
+        let output = SharedOutput::default();
+        let mut state = OutputStreamState::new(
+            output.clone(),
+            three_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Csv);
+
+        // the first batch is printed with the header before the next arrives
+        let batches = split_batch(three_column_batch());
+        state.process_batch(&batches[0]).unwrap();
+        assert_eq!(output.contents(), "a,b,c\n1,4,7\n");
+
+        state.process_batch(&batches[1]).unwrap();
+        state.finish().unwrap();
+        assert_eq!(output.contents(), "a,b,c\n1,4,7\n2,5,8\n3,6,9\n");
+    }
+
+    #[test]
+    fn print_stream_tsv() {
+        let output = SharedOutput::default();
+        let mut state = OutputStreamState::new(
+            output.clone(),
+            three_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Tsv);
+
+        // the header precedes the first batch only
+        let batches = split_batch(three_column_batch());
+        state.process_batch(&batches[0]).unwrap();
+        assert_eq!(output.contents(), "a\tb\tc\n1\t4\t7\n");
+
+        state.process_batch(&batches[1]).unwrap();
+        state.finish().unwrap();
+        assert_eq!(output.contents(), "a\tb\tc\n1\t4\t7\n2\t5\t8\n3\t6\t9\n");
+
+        // the row limit and header of the options apply to the stream
+        let mut output: Vec<u8> = vec![];
+        let mut state = OutputStreamState::new(
+            &mut output,
+            three_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Tsv)
+        .with_options(
+            PrintBatchesOptions::new()
+                .with_maxrows(MaxRows::Limited(2))
+                .with_header(false),
+        );
+        for batch in &batches {
+            state.process_batch(batch).unwrap();
+        }
+        state.finish().unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "1\t4\t7\n2\t5\t8\n");
+    }
 
     #[test]
-    fn print_empty() {
-        for format in [
-            PrintFormat::Csv,
-            PrintFormat::Tsv,
-            PrintFormat::Json,
-            PrintFormat::NdJson,
-            PrintFormat::Automatic,
-        ] {
-            // no output for empty batches, even with header set
-            let output = PrintBatchesTest::new()
+    fn print_stream_keeps_the_csv_writer() {
+        // the lines describing the output and the header precede the first
+        // batch only, and every batch is written as soon as it arrives
+        let options = PrintBatchesOptions::new()
+            .with_csv_bom(true)
+            .with_descriptor_line(true)
+            .with_source_sql(Some("SELECT a, b, c FROM t".into()));
+        let batches = split_batch(three_column_batch());
+        for format in [PrintFormat::Csv, PrintFormat::Tsv] {
+            let output = SharedOutput::default();
+            let mut state = OutputStreamState::new(
+                output.clone(),
+                three_column_schema(),
+                MaxRows::Unlimited,
+                &FormatOptions::default(),
+            )
+            .with_format(format)
+            .with_options(options.clone());
+            let mut written = vec![];
+            for batch in &batches {
+                state.process_batch(batch).unwrap();
+                written.push(output.contents());
+            }
+            state.finish().unwrap();
+            assert!(written.windows(2).all(|pair| pair[0].len() < pair[1].len()));
+
+            // the same as printing all the batches at once
+            let expected = PrintBatchesTest::new()
                 .with_format(format)
                 .with_schema(three_column_schema())
-                .with_batches(vec![])
+                .with_batches(batches.clone())
+                .with_header(WithHeader::Yes)
+                .with_options(options.clone())
                 .run();
-            assert_eq!(output, "")
+            assert_eq!(output.contents(), expected, "{format}");
         }
-
-        // output column headers for empty batches when format is Table
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Table)
-            .with_schema(three_column_schema())
-            .with_batches(vec![])
-            .run();
-        assert_snapshot!(output, @r#"
-        +---+---+---+
-        | a | b | c |
-        +---+---+---+
-        +---+---+---+
-        "#);
     }
 
     #[test]
-    fn print_csv_no_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Csv)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::No)
-            .run();
-        assert_snapshot!(output, @r#"
-        1,4,7
-        2,5,8
-        3,6,9
-        "#);
-    }
+    fn print_stream_json() {
+        let batches = split_batch(three_column_batch());
+        for format in [PrintFormat::Json, PrintFormat::NdJson] {
+            for ndjson_trailing_newline in [true, false] {
+                let options = PrintBatchesOptions::new()
+                    .with_ndjson_trailing_newline(ndjson_trailing_newline);
+                let output = SharedOutput::default();
+                let mut state = OutputStreamState::new(
+                    output.clone(),
+                    three_column_schema(),
+                    MaxRows::Unlimited,
+                    &FormatOptions::default(),
+                )
+                .with_format(format)
+                .with_options(options.clone());
+                state.process_batch(&batches[0]).unwrap();
+                assert!(!output.contents().is_empty());
+                state.process_batch(&batches[1]).unwrap();
+                state.finish().unwrap();
 
-    #[test]
-    fn print_csv_with_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Csv)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Yes)
-            .run();
-        assert_snapshot!(output, @r#"
-        a,b,c
-        1,4,7
-        2,5,8
-        3,6,9
-        "#);
+                // the same as printing all the batches at once
+                let expected = PrintBatchesTest::new()
+                    .with_format(format)
+                    .with_schema(three_column_schema())
+                    .with_batches(batches.clone())
+                    .with_header(WithHeader::Yes)
+                    .with_options(options)
+                    .run();
+                assert_eq!(output.contents(), expected);
+            }
+        }
     }
 
     #[test]
-    fn print_tsv_no_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Tsv)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::No)
-            .run();
-        assert_snapshot!(output, @"
-        1\t4\t7
-        2\t5\t8
-        3\t6\t9
-        ")
+    fn print_stream_buffered_format() {
+        let output = SharedOutput::default();
+        let mut state = OutputStreamState::new(
+            output.clone(),
+            one_column_schema(),
+            MaxRows::Unlimited,
+            &FormatOptions::default(),
+        )
+        .with_format(PrintFormat::Markdown);
+        for batch in split_batch(one_column_batch()) {
+            state.process_batch(&batch).unwrap();
+        }
+        // the table is printed once all the rows are known
+        assert!(output.contents().is_empty());
+        state.finish().unwrap();
+        assert_snapshot!(output.contents(), @r#"
+        | a |
+        |---|
+        | 1 |
+        | 2 |
+        | 3 |
+        "#);
     }
 
     #[test]
-    fn print_tsv_with_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Tsv)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Yes)
-            .run();
-        assert_snapshot!(output, @"
-        a\tb\tc
-        1\t4\t7
-        2\t5\t8
-        3\t6\t9
-        ");
-    }
+    fn table_layout() {
+        let batches = split_batch(three_column_batch());
+        let layout = PrintFormat::Table
+            .layout(
+                three_column_schema(),
+                &batches,
+                &PrintBatchesOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(layout.headers, vec!["a", "b", "c"]);
+        assert_eq!(layout.widths, vec![1, 1, 1]);
+        assert_eq!(
+            layout.rows,
+            vec![
+                vec!["1", "4", "7"],
+                vec!["2", "5", "8"],
+                vec!["3", "6", "9"]
+            ]
+        );
 
-    #[test]
-    fn print_table() {
+        // the layout matches the rendered table
         let output = PrintBatchesTest::new()
             .with_format(PrintFormat::Table)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Ignored)
+            .with_batches(batches)
             .run();
-        assert_snapshot!(output, @r#"
-        +---+---+---+
-        | a | b | c |
-        +---+---+---+
-        | 1 | 4 | 7 |
-        | 2 | 5 | 8 |
-        | 3 | 6 | 9 |
-        +---+---+---+
-        "#);
+        let lines: Vec<&str> = output.lines().collect();
+        let rows = std::iter::once(&layout.headers).chain(&layout.rows);
+        for (row, line) in rows.zip([lines[1], lines[3], lines[4], lines[5]]) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&layout.widths)
+                .map(|(cell, width)| format!(" {cell:<width$} "))
+                .collect();
+            assert_eq!(line, format!("|{}|", cells.join("|")));
+        }
     }
+
     #[test]
-    fn print_json() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Json)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Ignored)
-            .run();
-        assert_snapshot!(output, @r#"
-        [{"a":1,"b":4,"c":7},{"a":2,"b":5,"c":8},{"a":3,"b":6,"c":9}]
-        "#);
+    fn write_partitioned() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("region", DataType::Utf8, false),
+            Field::new("value", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["east", "west", "east"])),
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+            ],
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let write = |options: &PrintBatchesOptions| {
+            PrintFormat::Csv.write_partitioned(
+                dir.path(),
+                Arc::clone(&schema),
+                &[batch.clone()],
+                &PartitionColumn::new("region"),
+                options,
+            )
+        };
+
+        let paths = write(&PrintBatchesOptions::default()).unwrap();
+        assert_eq!(
+            paths,
+            vec![dir.path().join("east.csv"), dir.path().join("west.csv")]
+        );
+        assert_eq!(std::fs::read_to_string(&paths[0]).unwrap(), "value\n1\n3\n");
+        assert_eq!(std::fs::read_to_string(&paths[1]).unwrap(), "value\n2\n");
+
+        // the files of an earlier export are only replaced when forced
+        let err = write(&PrintBatchesOptions::default()).unwrap_err();
+        assert_contains!(err.to_string(), "east.csv already exists");
+        let options = PrintBatchesOptions::new().with_force_overwrite(true);
+        assert_eq!(write(&options).unwrap(), paths);
     }
 
     #[test]
-    fn print_ndjson() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::NdJson)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Ignored)
-            .run();
-        assert_snapshot!(output, @r#"
-        {"a":1,"b":4,"c":7}
-        {"a":2,"b":5,"c":8}
-        {"a":3,"b":6,"c":9}
-        "#);
+    fn print_parquet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.parquet");
+        let batches = split_batch(three_column_batch());
+
+        let mut output: Vec<u8> = vec![];
+        PrintFormat::Parquet
+            .print_with_options(
+                &mut output,
+                three_column_schema(),
+                &batches,
+                &PrintBatchesOptions::new().with_output_path(Some(path.clone())),
+            )
+            .unwrap();
+        assert!(output.is_empty());
+
+        let file = std::fs::File::open(&path).unwrap();
+        let read = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            arrow::compute::concat_batches(&three_column_schema(), &read).unwrap(),
+            three_column_batch()
+        );
     }
 
     #[test]
-    fn print_automatic_no_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Automatic)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::No)
-            .run();
-        assert_snapshot!(output, @r#"
-        1,4,7
-        2,5,8
-        3,6,9
-        "#);
+    fn print_parquet_existing_output_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.parquet");
+        std::fs::write(&path, "earlier export").unwrap();
+
+        let options = PrintBatchesOptions::new().with_output_path(Some(path.clone()));
+        let err = PrintFormat::Parquet
+            .print_with_options(
+                &mut std::io::sink(),
+                three_column_schema(),
+                &[three_column_batch()],
+                &options,
+            )
+            .unwrap_err();
+        assert_contains!(
+            err.to_string(),
+            "already exists, use --force to overwrite it"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "earlier export");
+
+        PrintFormat::Parquet
+            .print_with_options(
+                &mut std::io::sink(),
+                three_column_schema(),
+                &[three_column_batch()],
+                &options.with_force_overwrite(true),
+            )
+            .unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let rows = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum::<usize>();
+        assert_eq!(rows, 3);
     }
+
     #[test]
-    fn print_automatic_with_header() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Automatic)
-            .with_batches(split_batch(three_column_batch()))
-            .with_header(WithHeader::Yes)
-            .run();
-        assert_snapshot!(output, @r#"
-        a,b,c
-        1,4,7
-        2,5,8
-        3,6,9
-        "#);
+    fn create_output_file_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+
+        let mut writer =
+            std::io::BufWriter::new(create_output_file(&path, false).unwrap());
+        writeln!(writer, "a").unwrap();
+        sync_output_file(writer).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+
+        let err = create_output_file(&path, false).unwrap_err();
+        assert_contains!(err.to_string(), "out.csv already exists");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\n");
+
+        // forcing truncates the existing file
+        let mut writer =
+            std::io::BufWriter::new(create_output_file(&path, true).unwrap());
+        writeln!(writer, "b").unwrap();
+        sync_output_file(writer).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b\n");
     }
 
     #[test]
-    fn print_maxrows_unlimited() {
-        // should print out entire output with no truncation if unlimited or
-        // limit greater than number of batches or equal to the number of batches
-        for max_rows in [MaxRows::Unlimited, MaxRows::Limited(5), MaxRows::Limited(3)] {
-            let output = PrintBatchesTest::new()
-                .with_format(PrintFormat::Table)
-                .with_schema(one_column_schema())
-                .with_batches(vec![one_column_batch()])
-                .with_maxrows(max_rows)
-                .run();
-            allow_duplicates! {
-                assert_snapshot!(output, @r#"
-                +---+
-                | a |
-                +---+
-                | 1 |
-                | 2 |
-                | 3 |
-                +---+
-                "#);
-            }
-        }
+    fn print_parquet_requires_output_path() {
+        let err = PrintFormat::Parquet
+            .print_with_options(
+                &mut std::io::sink(),
+                three_column_schema(),
+                &[three_column_batch()],
+                &PrintBatchesOptions::new(),
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "an output path is required");
     }
 
     #[test]
-    fn print_maxrows_limited_one_batch() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Table)
-            .with_batches(vec![one_column_batch()])
-            .with_maxrows(MaxRows::Limited(1))
-            .run();
-        assert_snapshot!(output, @r#"
-        +---+
-        | a |
-        +---+
-        | 1 |
-        | . |
-        | . |
-        | . |
-        +---+
-        "#);
+    fn print_parquet_base64_wrap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.parquet");
+        let mut output: Vec<u8> = vec![];
+        let err = PrintFormat::Parquet
+            .print_with_options(
+                &mut output,
+                three_column_schema(),
+                &[three_column_batch()],
+                &PrintBatchesOptions::new()
+                    .with_output_path(Some(path.clone()))
+                    .with_base64_wrap(true),
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "cannot be base64 wrapped");
+        assert!(output.is_empty());
+        assert!(!path.exists());
     }
 
     #[test]
-    fn print_maxrows_limited_multi_batched() {
-        let output = PrintBatchesTest::new()
-            .with_format(PrintFormat::Table)
-            .with_batches(vec![
-                one_column_batch(),
-                one_column_batch(),
-                one_column_batch(),
-            ])
-            .with_maxrows(MaxRows::Limited(5))
-            .run();
-        assert_snapshot!(output, @r#"
-        +---+
-        | a |
-        +---+
-        | 1 |
-        | 2 |
-        | 3 |
-        | 1 |
-        | 2 |
-        | . |
-        | . |
-        | . |
-        +---+
-        "#);
+    fn print_arrow_stream() {
+        for batches in [split_batch(three_column_batch()), vec![]] {
+            let mut output: Vec<u8> = vec![];
+            PrintFormat::Arrow
+                .print_with_options(
+                    &mut output,
+                    three_column_schema(),
+                    &batches,
+                    &PrintBatchesOptions::new()
+                        .with_maxrows(MaxRows::Limited(1))
+                        .with_header(false),
+                )
+                .unwrap();
+
+            let reader = StreamReader::try_new(output.as_slice(), None).unwrap();
+            assert_eq!(reader.schema(), three_column_schema());
+            let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(read, batches);
+        }
     }
 
     #[test]
@@ -504,6 +10391,138 @@ mod tests {
         assert_eq!(output, "")
     }
 
+    #[test]
+    fn print_table_normalize_unicode() {
+        // "e" followed by a combining acute accent, and a precomposed "é"
+        let schema = Arc::new(Schema::new(vec![Field::new("s", DataType::Utf8, false)]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![Arc::new(StringArray::from(vec!["e\u{301}x", "\u{e9}x"]))],
+        )
+        .unwrap();
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Table)
+            .with_batches(vec![batch])
+            .with_options(
+                PrintBatchesOptions::new()
+                    .with_normalize_unicode(Some(NormalizationForm::Nfc)),
+            )
+            .run();
+        assert!(!output.contains('\u{301}'));
+        assert_snapshot!(output, @r#"
+        +----+
+        | s  |
+        +----+
+        | éx |
+        | éx |
+        +----+
+        "#);
+    }
+
+    #[test]
+    fn print_pivot() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("region", DataType::Utf8, false),
+            Field::new("quarter", DataType::Utf8, false),
+            Field::new("sales", DataType::Int32, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec!["east", "east", "west"])),
+                Arc::new(StringArray::from(vec!["q1", "q2", "q1"])),
+                Arc::new(Int32Array::from(vec![10, 20, 30])),
+            ],
+        )
+        .unwrap();
+        let options = PrintBatchesOptions::new()
+            .with_pivot(Some(PivotColumns::new("region", "quarter", "sales")));
+
+        let output = PrintBatchesTest::new()
+            .with_format(PrintFormat::Pivot)
+            .with_schema(Arc::clone(&schema))
+            .with_batches(split_batch(batch.clone()))
+            .with_options(options.clone())
+            .run();
+        assert_snapshot!(output, @r#"
+        +--------+----+----+
+        | region | q1 | q2 |
+        +--------+----+----+
+        | east   | 10 | 20 |
+        | west   | 30 |    |
+        +--------+----+----+
+        "#);
+
+        // more than one value for the same cell is an error
+        let mut buffer: Vec<u8> = vec![];
+        let err = PrintFormat::Pivot
+            .print_with_options(
+                &mut buffer,
+                Arc::clone(&schema),
+                &[batch.clone(), batch],
+                &options.clone(),
+            )
+            .unwrap_err();
+        assert_contains!(err.to_string(), "multiple values of 'sales'");
+    }
+
+    #[test]
+    fn pivot_keeps_nulls() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("region", DataType::Utf8, true),
+            Field::new("quarter", DataType::Utf8, true),
+            Field::new("sales", DataType::Int32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("east"),
+                    Some("NULL"),
+                    None,
+                    Some("east"),
+                ])),
+                Arc::new(StringArray::from(vec![
+                    Some("NULL"),
+                    Some("q1"),
+                    Some("q1"),
+                    None,
+                ])),
+                Arc::new(Int32Array::from(vec![None, Some(10), Some(20), Some(30)])),
+            ],
+        )
+        .unwrap();
+        let format_options = FormatOptions {
+            null: "NULL".to_string(),
+            ..Default::default()
+        };
+        let pivoted = pivot_batches(
+            &[batch],
+            &schema,
+            &PivotColumns::new("region", "quarter", "sales"),
+            &format_options,
+        )
+        .unwrap();
+
+        // the null keys are rows and columns of their own, apart from the
+        // `NULL` strings
+        let schema = pivoted.schema();
+        let names: Vec<&str> =
+            schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ["region", "NULL", "q1", "NULL"]);
+        let regions = pivoted.column(0).as_string::<i32>();
+        assert_eq!(
+            regions.iter().collect::<Vec<_>>(),
+            [Some("east"), Some("NULL"), None]
+        );
+        let east = |column: usize| pivoted.column(column).as_string::<i32>().is_null(0);
+        // the null measure is a null rather than the null string
+        assert!(east(1));
+        assert!(east(2));
+        assert_eq!(pivoted.column(3).as_string::<i32>().value(0), "30");
+    }
+
     #[derive(Debug)]
     struct PrintBatchesTest {
         format: PrintFormat,
@@ -511,6 +10530,7 @@ mod tests {
         batches: Vec<RecordBatch>,
         maxrows: MaxRows,
         with_header: WithHeader,
+        options: PrintBatchesOptions,
     }
 
     /// How to test with_header
@@ -530,6 +10550,7 @@ mod tests {
                 batches: vec![],
                 maxrows: MaxRows::Unlimited,
                 with_header: WithHeader::Ignored,
+                options: PrintBatchesOptions::default(),
             }
         }
 
@@ -563,6 +10584,12 @@ mod tests {
             self
         }
 
+        /// set the additional print options
+        fn with_options(mut self, options: PrintBatchesOptions) -> Self {
+            self.options = options;
+            self
+        }
+
         /// run the test
         /// formats batches using parameters and returns the resulting output
         fn run(self) -> String {
@@ -584,14 +10611,17 @@ mod tests {
 
         fn output_with_header(&self, with_header: bool) -> String {
             let mut buffer: Vec<u8> = vec![];
+            let options = self
+                .options
+                .clone()
+                .with_maxrows(self.maxrows)
+                .with_header(with_header);
             self.format
-                .print_batches(
+                .print_with_options(
                     &mut buffer,
                     self.schema.clone(),
                     &self.batches,
-                    self.maxrows,
-                    with_header,
-                    &FormatOptions::default(),
+                    &options,
                 )
                 .unwrap();
             String::from_utf8(buffer).unwrap()
@@ -15,22 +15,31 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::io::Write;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::print_format::PrintFormat;
+use crate::print_format::{
+    create_output_file, sync_output_file, Alignment, BinaryEncoding, BorderStyle,
+    ChunkedWriter, OutputStreamState, PrintBatchesOptions, PrintFormat, QuoteStyle,
+};
 
 use arrow::datatypes::SchemaRef;
 use arrow::record_batch::RecordBatch;
+use datafusion::common::exec_err;
 use datafusion::common::instant::Instant;
-use datafusion::common::DataFusionError;
 use datafusion::error::Result;
 use datafusion::physical_plan::RecordBatchStream;
 
 use datafusion::config::FormatOptions;
 use futures::StreamExt;
+use parking_lot::Mutex;
 
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum MaxRows {
@@ -67,12 +76,152 @@ impl Display for MaxRows {
     }
 }
 
+/// When to use ANSI colors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Use colors when stdout is a terminal
+    Auto,
+    Always,
+    #[default]
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to use colors
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Auto => std::io::stdout().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// The [`PrintOptions::output`] file once the first query of a session
+/// created it, shared by the clones of the options so the results of the
+/// next queries are appended rather than replace it
+#[derive(Debug, Clone, Default)]
+pub struct OutputFile(Arc<Mutex<OutputFileState>>);
+
+#[derive(Debug, Default)]
+struct OutputFileState {
+    /// The file the results of text formats are appended to. The Parquet
+    /// writer creates its file itself, once the results are complete.
+    file: Option<File>,
+    /// Whether the results of a query were written to the file
+    written: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct PrintOptions {
     pub format: PrintFormat,
     pub quiet: bool,
     pub maxrows: MaxRows,
-    pub color: bool,
+    /// Whether [`Self::maxrows`] was given rather than defaulted. The default
+    /// only limits Table output, not other formats or an [`Self::output`]
+    /// file.
+    pub maxrows_explicit: bool,
+    /// Whether to highlight SQL syntax and colorize Table output, see
+    /// [`PrintBatchesOptions::colorize`]
+    pub color: ColorChoice,
+    /// Print the format [`PrintFormat::Automatic`] resolves to, and why, to
+    /// stderr
+    pub log_automatic_decision: bool,
+    /// The string nulls are printed as in Table, Csv and Tsv output, instead
+    /// of the `null` of the session's format options
+    pub null_string: Option<String>,
+    /// The maximum display width of a column, see
+    /// [`PrintBatchesOptions::max_column_width`]
+    pub max_col_width: Option<usize>,
+    /// Which Csv and Tsv fields are quoted
+    pub quote_style: QuoteStyle,
+    /// The character Csv and Tsv fields are quoted with
+    pub quote_char: char,
+    /// Number the rows of Table output, see
+    /// [`PrintBatchesOptions::row_numbers`]
+    pub row_numbers: bool,
+    /// The width Table output is shrunk to fit, see
+    /// [`PrintBatchesOptions::fit_width`]
+    pub fit_width: Option<usize>,
+    /// The characters Table borders are drawn with
+    pub border_style: BorderStyle,
+    /// The number of dotted lines marking omitted Table rows, see
+    /// [`PrintBatchesOptions::truncation_rows`]
+    pub truncation_rows: usize,
+    /// Print the Table header again every this many rows, see
+    /// [`PrintBatchesOptions::header_interval`]
+    pub header_interval: Option<usize>,
+    /// The alignment of Table columns by name or index, see
+    /// [`PrintBatchesOptions::column_alignments`]
+    pub column_alignments: HashMap<String, Alignment>,
+    /// End Csv and Tsv output with a `# N rows` comment, see
+    /// [`PrintBatchesOptions::row_count_comment`]
+    pub row_count_comment: bool,
+    /// Start Csv and Tsv output with a UTF-8 byte order mark
+    pub csv_bom: bool,
+    /// The number of decimal places of floating point and decimal values,
+    /// see [`PrintBatchesOptions::float_precision`]
+    pub float_precision: Option<usize>,
+    /// Print floating point and decimal values in scientific notation
+    pub float_scientific: bool,
+    /// The separator of the digit groups of Table integers and decimals,
+    /// see [`PrintBatchesOptions::group_digits`]
+    pub group_digits: Option<char>,
+    /// How binary values are printed, except in Json output
+    pub binary_encoding: BinaryEncoding,
+    /// The names of the only columns printed, in their order
+    pub display_columns: Option<Vec<String>>,
+    /// Print the columns of the results, see [`PrintFormat::print_schema`],
+    /// instead of their rows
+    pub describe: bool,
+    /// The file the results are written to instead of stdout, which also
+    /// sets the format of [`PrintFormat::Automatic`] by its extension
+    pub output: Option<PathBuf>,
+    /// Overwrite an existing [`Self::output`] file
+    pub force: bool,
+    /// The [`Self::output`] file, created by the first query of the session
+    pub output_file: OutputFile,
+    /// Coalesce the output of streamed batches into chunks of at least this
+    /// many bytes before writing them to stdout, see [`ChunkedWriter`]
+    pub chunk_target_bytes: usize,
+    /// The longest streamed output is held back while a chunk fills up
+    pub max_flush_delay: Option<Duration>,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            format: PrintFormat::Automatic,
+            quiet: false,
+            maxrows: MaxRows::Unlimited,
+            maxrows_explicit: false,
+            color: ColorChoice::default(),
+            log_automatic_decision: false,
+            null_string: None,
+            max_col_width: None,
+            quote_style: QuoteStyle::default(),
+            quote_char: '"',
+            row_numbers: false,
+            fit_width: None,
+            border_style: BorderStyle::default(),
+            truncation_rows: 3,
+            header_interval: None,
+            column_alignments: HashMap::new(),
+            row_count_comment: false,
+            csv_bom: false,
+            float_precision: None,
+            float_scientific: false,
+            group_digits: None,
+            binary_encoding: BinaryEncoding::default(),
+            display_columns: None,
+            describe: false,
+            output: None,
+            force: false,
+            output_file: OutputFile::default(),
+            chunk_target_bytes: 0,
+            max_flush_delay: None,
+        }
+    }
 }
 
 // Returns the query execution details formatted
@@ -96,8 +245,147 @@ fn get_execution_details_formatted(
     )
 }
 
+/// The width `Table` output is shrunk to when stdout is a terminal that
+/// reports neither its width nor a `COLUMNS` environment variable
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// The width `Table` output is shrunk to: `width` if given, whether or not
+/// stdout is a terminal, otherwise the width of the terminal stdout is
+/// printed to, or `None` if stdout is not a terminal, such as when the
+/// output is piped to another program.
+///
+/// The width is asked of the terminal itself, falling back to the `COLUMNS`
+/// environment variable and then 80 columns when the terminal does not report
+/// one
+pub fn terminal_width(width: Option<usize>) -> Option<usize> {
+    if width.is_some() {
+        return width;
+    }
+    let stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return None;
+    }
+    let columns = std::env::var("COLUMNS").ok();
+    Some(
+        terminal_size::terminal_size_of(stdout)
+            .map(|(terminal_size::Width(width), _)| usize::from(width))
+            .filter(|width| *width > 0)
+            .or_else(|| columns.and_then(|columns| columns.parse().ok()))
+            .unwrap_or(DEFAULT_TERMINAL_WIDTH),
+    )
+}
+
+/// Treat writing to a closed pipe, such as when the output is piped to
+/// `head`, as success, like [`PrintBatchesOptions::ignore_broken_pipe`]
+fn ignore_broken_pipe(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
 impl PrintOptions {
-    /// Print the batches to stdout using the specified format
+    /// The session's `format_options` with any [`Self::null_string`] applied
+    fn format_options(&self, format_options: &FormatOptions) -> FormatOptions {
+        let mut format_options = format_options.clone();
+        if let Some(null_string) = &self.null_string {
+            format_options.null = null_string.clone();
+        }
+        format_options
+    }
+
+    /// The [`PrintBatchesOptions`] set by these options
+    fn batches_options(&self) -> PrintBatchesOptions {
+        let appending = self.output_file.0.lock().written;
+        PrintBatchesOptions::new()
+            .with_max_column_width(self.max_col_width)
+            .with_csv_quote_style(self.quote_style)
+            .with_csv_quote(self.quote_char)
+            .with_row_numbers(self.row_numbers)
+            .with_fit_width(self.fit_width)
+            .with_colorize(self.colorize())
+            .with_border_style(self.border_style)
+            .with_truncation_rows(self.truncation_rows)
+            .with_header_interval(self.header_interval)
+            .with_column_alignments(self.column_alignments.clone())
+            .with_row_count_comment(self.row_count_comment)
+            // the results appended to the output file continue the rows of
+            // the first query, under its header
+            .with_header(!appending)
+            .with_csv_bom(self.csv_bom && !appending)
+            .with_float_precision(self.float_precision)
+            .with_float_scientific(self.float_scientific)
+            .with_group_digits(self.group_digits)
+            .with_binary_encoding(self.binary_encoding)
+            .with_display_columns(self.display_columns.clone())
+            .with_output_path(self.output.clone())
+            .with_force_overwrite(self.force)
+    }
+
+    /// Whether to colorize the results, which [`ColorChoice::Auto`] only does
+    /// when they are printed to a terminal rather than the [`Self::output`]
+    /// file
+    fn colorize(&self) -> bool {
+        match (self.color, &self.output) {
+            (ColorChoice::Auto, Some(_)) => false,
+            (color, _) => color.enabled(),
+        }
+    }
+
+    /// Open the [`Self::output`] file, if any, which the first query of the
+    /// session creates and the next ones append to, unless the format
+    /// writes the file itself. A binary format holds a single query.
+    fn open_output(&self) -> Result<Option<BufWriter<File>>> {
+        let Some(path) = &self.output else {
+            return Ok(None);
+        };
+        let mut output_file = self.output_file.0.lock();
+        let binary = matches!(self.format, PrintFormat::Parquet | PrintFormat::Arrow);
+        if output_file.written && binary {
+            return exec_err!(
+                "{} already holds the results of a query, and {} output cannot be appended to",
+                path.display(),
+                self.format.output_name()
+            );
+        }
+        // the Parquet writer creates the file, so a failed query leaves none
+        if self.format == PrintFormat::Parquet {
+            return Ok(None);
+        }
+        let file = match output_file.file.take() {
+            Some(file) => file,
+            None => create_output_file(path, self.force)?,
+        };
+        let file = output_file.file.insert(file);
+        // the clone shares the offset of the file, after the earlier results
+        Ok(Some(BufWriter::new(file.try_clone()?)))
+    }
+
+    /// Sync the [`Self::open_output`] file, and record that the results of a
+    /// query were written to the [`Self::output`] file, if any
+    fn finish_output(&self, output: Option<BufWriter<File>>) -> Result<()> {
+        if let Some(file) = output {
+            sync_output_file(file)?;
+        }
+        if self.output.is_some() {
+            self.output_file.0.lock().written = true;
+        }
+        Ok(())
+    }
+
+    /// These options printing to stdout, for results without columns, such
+    /// as of DDL statements, to leave the [`Self::output`] file to a later
+    /// query. Nothing is printed for them as a Table.
+    fn without_output(&self) -> Self {
+        Self {
+            output: None,
+            format: PrintFormat::Table,
+            ..self.clone()
+        }
+    }
+
+    /// Print the batches to stdout, or the output file, using the specified
+    /// format
     pub fn print_batches(
         &self,
         schema: SchemaRef,
@@ -106,77 +394,131 @@ impl PrintOptions {
         row_count: usize,
         format_options: &FormatOptions,
     ) -> Result<()> {
+        if self.output.is_some() && schema.fields().is_empty() {
+            return self.without_output().print_batches(
+                schema,
+                batches,
+                query_start_time,
+                row_count,
+                format_options,
+            );
+        }
+
         let stdout = std::io::stdout();
         let mut writer = stdout.lock();
+        let mut output = self.open_output()?;
+        let mut results: &mut dyn Write = match &mut output {
+            Some(file) => file,
+            None => &mut writer,
+        };
 
-        self.format.print_batches(
-            &mut writer,
-            schema,
-            batches,
-            self.maxrows,
-            true,
-            format_options,
-        )?;
-
-        let formatted_exec_details = get_execution_details_formatted(
-            row_count,
-            if self.format == PrintFormat::Table {
-                self.maxrows
-            } else {
-                MaxRows::Unlimited
-            },
-            query_start_time,
-        );
+        let options = self
+            .batches_options()
+            .with_maxrows(self.maxrows)
+            .with_format_options(self.format_options(format_options));
+        self.format
+            .print_with_options(&mut results, schema, batches, &options)?;
+        self.finish_output(output)?;
+
+        let formatted_exec_details =
+            get_execution_details_formatted(row_count, self.maxrows, query_start_time);
 
         if !self.quiet {
-            writeln!(writer, "{formatted_exec_details}")?;
+            ignore_broken_pipe(writeln!(writer, "{formatted_exec_details}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Print the columns of `schema` to stdout, or the output file, using the
+    /// specified format, see [`PrintFormat::print_schema`]
+    pub fn print_schema(
+        &self,
+        schema: &SchemaRef,
+        format_options: &FormatOptions,
+    ) -> Result<()> {
+        if self.output.is_some() && schema.fields().is_empty() {
+            return self.without_output().print_schema(schema, format_options);
         }
 
+        let stdout = std::io::stdout();
+        let mut writer = stdout.lock();
+        let mut output = self.open_output()?;
+        let mut results: &mut dyn Write = match &mut output {
+            Some(file) => file,
+            None => &mut writer,
+        };
+
+        let options = self
+            .batches_options()
+            .with_format_options(self.format_options(format_options));
+        self.format.print_schema(&mut results, schema, &options)?;
+        self.finish_output(output)?;
         Ok(())
     }
 
-    /// Print the stream to stdout using the specified format
+    /// Print the stream to stdout, or the output file, using the specified
+    /// format
     pub async fn print_stream(
         &self,
         mut stream: Pin<Box<dyn RecordBatchStream>>,
         query_start_time: Instant,
         format_options: &FormatOptions,
     ) -> Result<()> {
-        if self.format == PrintFormat::Table {
-            return Err(DataFusionError::External(
-                "PrintFormat::Table is not implemented".to_string().into(),
-            ));
-        };
+        if self.output.is_some() && stream.schema().fields().is_empty() {
+            let print_options = self.without_output();
+            return Box::pin(print_options.print_stream(
+                stream,
+                query_start_time,
+                format_options,
+            ))
+            .await;
+        }
 
         let stdout = std::io::stdout();
-        let mut writer = stdout.lock();
+        let mut stdout = stdout.lock();
+        let mut output = self.open_output()?;
+        let results: &mut dyn Write = match &mut output {
+            Some(file) => file,
+            None => &mut stdout,
+        };
+        let mut writer =
+            ChunkedWriter::new(results, self.chunk_target_bytes, self.max_flush_delay);
+        let format_options = &self.format_options(format_options);
 
         let mut row_count = 0_usize;
-        let mut with_header = true;
+        let mut state = OutputStreamState::new(
+            &mut writer,
+            stream.schema(),
+            self.maxrows,
+            format_options,
+        )
+        .with_format(self.format)
+        .with_options(
+            self.batches_options()
+                .with_maxrows(self.maxrows)
+                .with_format_options(format_options.clone()),
+        );
 
         while let Some(maybe_batch) = stream.next().await {
             let batch = maybe_batch?;
             row_count += batch.num_rows();
-            self.format.print_batches(
-                &mut writer,
-                batch.schema(),
-                &[batch],
-                MaxRows::Unlimited,
-                with_header,
-                format_options,
-            )?;
-            with_header = false;
+            state.process_batch(&batch)?;
         }
+        state.finish()?;
+        // the results precede the execution details, which are printed to
+        // stdout even when the results are written to a file
+        ignore_broken_pipe(writer.finish())?;
+        drop(writer);
+        self.finish_output(output)?;
 
-        let formatted_exec_details = get_execution_details_formatted(
-            row_count,
-            MaxRows::Unlimited,
-            query_start_time,
-        );
+        let formatted_exec_details =
+            get_execution_details_formatted(row_count, self.maxrows, query_start_time);
 
         if !self.quiet {
-            writeln!(writer, "{formatted_exec_details}")?;
+            ignore_broken_pipe(writeln!(stdout, "{formatted_exec_details}"))?;
         }
+        ignore_broken_pipe(stdout.flush())?;
 
         Ok(())
     }
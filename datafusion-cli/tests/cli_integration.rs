@@ -128,7 +128,7 @@ fn init() {
 #[rstest]
 #[case::exec_multiple_statements(
     "statements",
-    ["--command", "select 1; select 2;", "-q"],
+    ["--command", "select 1; select 2;", "-q", "--format", "table"],
 )]
 #[case::exec_backslash(
     "backslash",
@@ -136,25 +136,46 @@ fn init() {
 )]
 #[case::exec_from_files(
     "files",
-    ["--file", "tests/sql/select.sql", "-q"],
+    ["--file", "tests/sql/select.sql", "-q", "--format", "table"],
 )]
 #[case::set_batch_size(
     "batch_size",
-    ["--command", "show datafusion.execution.batch_size", "-q", "-b", "1"],
+    ["--command", "show datafusion.execution.batch_size", "-q", "-b", "1", "--format", "table"],
 )]
 #[case::default_explain_plan(
     "default_explain_plan",
     // default explain format should be tree
-    ["--command", "EXPLAIN SELECT 123"],
+    ["--command", "EXPLAIN SELECT 123", "--format", "table"],
 )]
 #[case::can_see_indent_format(
     "can_see_indent_format",
     // can choose the old explain format too
-    ["--command", "EXPLAIN FORMAT indent SELECT 123"],
+    ["--command", "EXPLAIN FORMAT indent SELECT 123", "--format", "table"],
 )]
 #[case::change_format_version(
     "change_format_version",
-    ["--file", "tests/sql/types_format.sql", "-q"],
+    ["--file", "tests/sql/types_format.sql", "-q", "--format", "table"],
+)]
+#[case::null_string(
+    "null_string",
+    ["--command", "select CAST(NULL AS INT) AS a, 'x' AS b", "-q", "--format", "csv", "--null-string", "\\N"],
+)]
+#[case::quote_style(
+    "quote_style",
+    ["--command", "select 1 AS a, 'x' AS b", "-q", "--format", "csv", "--quote-style", "always"],
+)]
+#[case::row_numbers(
+    "row_numbers",
+    ["--command", "select * from (values (10), (20)) t(a)", "-q", "--format", "table", "--row-numbers"],
+)]
+#[case::describe(
+    "describe",
+    ["--command", "select 1 AS a, 'x' AS b", "-q", "--format", "table", "--describe"],
+)]
+#[case::bare_color(
+    "bare_color",
+    // a bare --color is auto, so piped output has no escape sequences
+    ["--command", "select 1 AS a", "-q", "--format", "table", "--color"],
 )]
 #[test]
 fn cli_quick_test<'a>(
@@ -180,8 +201,12 @@ fn cli_explain_environment_overrides() {
     let mut cmd = cli();
 
     // should use the environment variable to override the default explain plan
-    cmd.env("DATAFUSION_EXPLAIN_FORMAT", "pgjson")
-        .args(["--command", "EXPLAIN SELECT 123"]);
+    cmd.env("DATAFUSION_EXPLAIN_FORMAT", "pgjson").args([
+        "--command",
+        "EXPLAIN SELECT 123",
+        "--format",
+        "table",
+    ]);
 
     assert_cmd_snapshot!(cmd);
 }
@@ -192,7 +217,16 @@ fn cli_explain_environment_overrides() {
 #[case("table")]
 #[case("json")]
 #[case("nd-json")]
+#[case("json-pretty")]
 #[case("automatic")]
+#[case("fixed-width")]
+#[case("markdown")]
+#[case("html")]
+#[case("logfmt")]
+#[case("latex")]
+#[case("ini")]
+#[case("vertical")]
+#[case("json-with-schema")]
 #[test]
 fn test_cli_format<'a>(#[case] format: &'a str) {
     let mut settings = make_settings();
@@ -258,6 +292,7 @@ async fn test_cli() {
     glob!("sql/integration/*.sql", |path| {
         let input = fs::read_to_string(path).unwrap();
         assert_cmd_snapshot!(cli()
+            .args(["--format", "table"])
             .env_clear()
             .env("AWS_ACCESS_KEY_ID", "TEST-DataFusionLogin")
             .env("AWS_SECRET_ACCESS_KEY", "TEST-DataFusionPassword")
@@ -297,7 +332,10 @@ SELECT * FROM CARS limit 1;
 "#
     );
 
-    assert_cmd_snapshot!(cli().env_clear().pass_stdin(input));
+    assert_cmd_snapshot!(cli()
+        .args(["--format", "table"])
+        .env_clear()
+        .pass_stdin(input));
 }
 
 #[tokio::test]
@@ -328,6 +366,7 @@ SELECT COUNT(*) FROM hits;
     );
 
     assert_cmd_snapshot!(cli()
+        .args(["--format", "table"])
         .env("RUST_LOG", "warn")
         .env_remove("AWS_ENDPOINT")
         .pass_stdin(input));